@@ -0,0 +1,100 @@
+// Timings of the rule representations on the puzzle input and on large synthetic rule sets, see
+// `cargo test --release -- --ignored --nocapture fifth::benches`.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::pseudo_random::PseudoRandom;
+
+use super::{
+    bitset::{BitsetRules, PAGE_LIMIT},
+    count_valid_updates, parse_input, OrderingRules, PageOrderingRules,
+};
+
+const ROUNDS: u32 = 20;
+
+fn time_validation(rules: &impl OrderingRules, updates: &[Vec<u16>]) -> (usize, Duration) {
+    let instant = Instant::now();
+    let mut valid_updates = 0;
+
+    for _ in 0..ROUNDS {
+        valid_updates = count_valid_updates(rules, updates);
+    }
+
+    (valid_updates, instant.elapsed() / ROUNDS)
+}
+
+/// Rules that order all pages below `PAGE_LIMIT` completely, in a shuffled order, and updates of
+/// `update_length` pages each, every other one of them valid.
+fn synthetic_rules_and_updates(
+    update_length: usize,
+    update_count: usize,
+) -> (PageOrderingRules, Vec<Vec<u16>>) {
+    let mut random = PseudoRandom::new(7);
+
+    let mut order = (0..PAGE_LIMIT).collect::<Vec<_>>();
+    for i in (1..order.len()).rev() {
+        order.swap(i, random.below(i + 1));
+    }
+
+    let mut rules: HashMap<u16, Vec<u16>> = HashMap::new();
+    for (i, &before) in order.iter().enumerate() {
+        rules.insert(before, order[i + 1..].to_vec());
+    }
+
+    let updates = (0..update_count)
+        .map(|round| {
+            let mut pages = order.clone();
+            for i in 0..update_length {
+                let j = i + random.below(pages.len() - i);
+                pages.swap(i, j);
+            }
+            pages.truncate(update_length);
+
+            if round % 2 == 0 {
+                let ranks = order
+                    .iter()
+                    .enumerate()
+                    .map(|(rank, &page)| (page, rank))
+                    .collect::<HashMap<_, _>>();
+                pages.sort_by_key(|page| ranks[page]);
+            }
+
+            pages
+        })
+        .collect();
+
+    (PageOrderingRules { rules }, updates)
+}
+
+fn compare_representations(rules: &PageOrderingRules, updates: &[Vec<u16>]) {
+    let bitset_rules = BitsetRules::try_from(rules).unwrap();
+
+    let (hash_map_valid, hash_map_elapsed) = time_validation(rules, updates);
+    let (bitset_valid, bitset_elapsed) = time_validation(&bitset_rules, updates);
+
+    dbg!(updates.len(), hash_map_elapsed, bitset_elapsed);
+
+    assert_eq!(hash_map_valid, bitset_valid);
+}
+
+#[test]
+#[ignore]
+fn bench_rule_representations_on_input() {
+    let (rules, updates) = parse_input(include_str!("fifth.txt")).unwrap();
+
+    compare_representations(&rules, &updates);
+}
+
+#[test]
+#[ignore]
+fn bench_rule_representations_on_synthetic_rules() {
+    for update_length in [8, 32, 128] {
+        let (rules, updates) = synthetic_rules_and_updates(update_length, 1000);
+
+        dbg!(update_length);
+        compare_representations(&rules, &updates);
+    }
+}
@@ -0,0 +1,81 @@
+use super::{OrderingRules, PageOrderingRules, Rule};
+
+/// Pages have to be below this to fit into `BitsetRules`.
+pub const PAGE_LIMIT: u16 = 128;
+
+/// The rules as an adjacency matrix of bits, so that an update can be validated with one scan
+/// that collects the pages printed so far in a bitset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitsetRules {
+    /// Bit `after` of `pages_after[before]` is set if there is a rule `before|after`.
+    pages_after: Box<[u128; PAGE_LIMIT as usize]>,
+}
+
+impl Default for BitsetRules {
+    fn default() -> Self {
+        Self {
+            pages_after: Box::new([0; PAGE_LIMIT as usize]),
+        }
+    }
+}
+
+impl BitsetRules {
+    /// Returns whether the rule is new, or an error if one of its pages doesn't fit.
+    pub fn insert_rule(&mut self, rule: Rule) -> Result<bool, String> {
+        if rule.before >= PAGE_LIMIT || rule.after >= PAGE_LIMIT {
+            return Err(format!(
+                "rule {rule} doesn't fit, pages must be below {PAGE_LIMIT}"
+            ));
+        }
+
+        let is_new = !self.has_rule(rule.before, rule.after);
+        self.pages_after[usize::from(rule.before)] |= page_bit(rule.after);
+
+        Ok(is_new)
+    }
+}
+
+/// The bit of the page in a set of pages, nothing for pages that can't be in a rule.
+fn page_bit(page: u16) -> u128 {
+    1u128.checked_shl(page.into()).unwrap_or(0)
+}
+
+impl OrderingRules for BitsetRules {
+    fn has_rule(&self, before: u16, after: u16) -> bool {
+        self.pages_after
+            .get(usize::from(before))
+            .is_some_and(|pages_after| pages_after & page_bit(after) != 0)
+    }
+
+    fn is_valid_update(&self, update: &[u16]) -> bool {
+        let mut printed_pages = 0;
+
+        for &page in update {
+            // a page must not come after itself either
+            printed_pages |= page_bit(page);
+
+            let pages_after = self.pages_after.get(usize::from(page)).unwrap_or(&0);
+            if pages_after & printed_pages != 0 {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+impl TryFrom<&PageOrderingRules> for BitsetRules {
+    type Error = String;
+
+    fn try_from(rules: &PageOrderingRules) -> Result<Self, Self::Error> {
+        let mut bitset_rules = Self::default();
+
+        for (&before, pages_after) in &rules.rules {
+            for &after in pages_after {
+                bitset_rules.insert_rule(Rule { before, after })?;
+            }
+        }
+
+        Ok(bitset_rules)
+    }
+}
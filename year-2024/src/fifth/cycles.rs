@@ -0,0 +1,184 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+};
+
+use super::{PageOrderingRules, Rule};
+
+/// Rules that contradict each other: each rule's `after` is the next rule's `before`, and the last
+/// rule leads back to the first one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleCycle(pub Vec<Rule>);
+
+impl fmt::Display for RuleCycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, rule) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+
+            write!(f, "{rule}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Tarjan's algorithm for strongly connected components.
+struct ComponentSearch<'a> {
+    rules: &'a PageOrderingRules,
+    next_index: usize,
+    /// Visiting order and lowest visiting order reachable, for every visited page.
+    indices: HashMap<u16, (usize, usize)>,
+    stack: Vec<u16>,
+    on_stack: HashSet<u16>,
+    components: Vec<Vec<u16>>,
+}
+
+impl ComponentSearch<'_> {
+    fn visit(&mut self, page: u16) {
+        let index = self.next_index;
+        self.next_index += 1;
+        self.indices.insert(page, (index, index));
+        self.stack.push(page);
+        self.on_stack.insert(page);
+
+        for &later_page in self.rules.pages_after(page) {
+            let lowest_reachable = match self.indices.get(&later_page) {
+                None => {
+                    self.visit(later_page);
+                    self.indices[&later_page].1
+                }
+                Some(&(later_index, _)) if self.on_stack.contains(&later_page) => later_index,
+                // already part of a finished component
+                Some(_) => continue,
+            };
+
+            let (_, lowest) = self.indices.get_mut(&page).unwrap();
+            *lowest = (*lowest).min(lowest_reachable);
+        }
+
+        let (index, lowest) = self.indices[&page];
+        if index != lowest {
+            return;
+        }
+
+        // `page` is the first visited page of its component, which is on the stack above it
+        let mut component = Vec::new();
+        loop {
+            let component_page = self.stack.pop().unwrap();
+            self.on_stack.remove(&component_page);
+            component.push(component_page);
+
+            if component_page == page {
+                break;
+            }
+        }
+
+        component.sort_unstable();
+        self.components.push(component);
+    }
+}
+
+impl PageOrderingRules {
+    /// Groups of pages that each have to be printed before each other when following the rules,
+    /// which makes it impossible to print all pages of a group in one update. Both the groups and
+    /// the pages in them are sorted.
+    pub fn cyclic_components(&self) -> Vec<Vec<u16>> {
+        let mut search = ComponentSearch {
+            rules: self,
+            next_index: 0,
+            indices: HashMap::new(),
+            stack: Vec::new(),
+            on_stack: HashSet::new(),
+            components: Vec::new(),
+        };
+
+        for page in self.pages() {
+            if !search.indices.contains_key(&page) {
+                search.visit(page);
+            }
+        }
+
+        let mut cyclic_components = search
+            .components
+            .into_iter()
+            .filter(|component| {
+                // a single page only forms a cycle if it must be printed before itself
+                component.len() > 1 || self.pages_after(component[0]).contains(&component[0])
+            })
+            .collect::<Vec<_>>();
+
+        cyclic_components.sort_unstable();
+
+        cyclic_components
+    }
+
+    /// One of the shortest cycles in each cyclic component. There are far too many cycles in the
+    /// puzzle rules to list all of them.
+    pub fn cycles(&self) -> Vec<RuleCycle> {
+        self.cyclic_components()
+            .iter()
+            .filter_map(|component| self.find_cycle_among(component))
+            .collect()
+    }
+
+    /// Finds one of the shortest cycles among the rules between the given pages, starting with the
+    /// earliest page of a shortest cycle. An update with such a cycle can't be fixed, without one
+    /// `fix_update` always finds an order.
+    pub fn find_cycle_among(&self, pages: &[u16]) -> Option<RuleCycle> {
+        let page_set = pages.iter().copied().collect::<HashSet<_>>();
+        let mut shortest_cycle: Option<Vec<u16>> = None;
+
+        for &start in pages {
+            let Some(cycle) = self.shortest_cycle_through(start, &page_set) else {
+                continue;
+            };
+
+            if shortest_cycle
+                .as_ref()
+                .is_none_or(|shortest_cycle| cycle.len() < shortest_cycle.len())
+            {
+                shortest_cycle = Some(cycle);
+            }
+        }
+
+        let cycle = shortest_cycle?;
+        let rules = (0..cycle.len())
+            .map(|i| Rule {
+                before: cycle[i],
+                after: cycle[(i + 1) % cycle.len()],
+            })
+            .collect();
+
+        Some(RuleCycle(rules))
+    }
+
+    /// Breadth-first search for the shortest way back to `start`, returns the pages on the way
+    /// beginning with `start`.
+    fn shortest_cycle_through(&self, start: u16, pages: &HashSet<u16>) -> Option<Vec<u16>> {
+        let mut previous_pages: HashMap<u16, u16> = HashMap::new();
+        let mut queue = VecDeque::from([start]);
+
+        while let Some(page) = queue.pop_front() {
+            for &later_page in self.pages_after(page) {
+                if later_page == start {
+                    let mut cycle = vec![page];
+                    while let Some(&previous_page) = previous_pages.get(cycle.last().unwrap()) {
+                        cycle.push(previous_page);
+                    }
+                    cycle.reverse();
+
+                    return Some(cycle);
+                }
+
+                if pages.contains(&later_page) && !previous_pages.contains_key(&later_page) {
+                    previous_pages.insert(later_page, page);
+                    queue.push_back(later_page);
+                }
+            }
+        }
+
+        None
+    }
+}
@@ -0,0 +1,76 @@
+use std::fmt;
+
+use super::{page_positions, PageOrderingRules, Rule};
+
+/// A rule that an update breaks by printing `rule.after` before `rule.before`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Violation {
+    pub rule: Rule,
+    /// Where `rule.before` is in the update.
+    pub before_index: usize,
+    /// Where `rule.after` is in the update, never after `before_index`. They are the same for a
+    /// rule that puts a page before itself.
+    pub after_index: usize,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at index {} must come before {} at index {} (rule {} reversed)",
+            self.rule.before, self.before_index, self.rule.after, self.after_index, self.rule
+        )
+    }
+}
+
+impl PageOrderingRules {
+    /// Every rule the update breaks, ordered by the position of the page that is printed too late.
+    pub fn violations(&self, update: &[u16]) -> Vec<Violation> {
+        let positions = page_positions(update);
+        let mut violations = Vec::new();
+
+        for (before_index, &page) in update.iter().enumerate() {
+            for &later_page in self.pages_after(page) {
+                let Some(&after_index) = positions.get(&later_page) else {
+                    continue;
+                };
+
+                if after_index <= before_index {
+                    violations.push(Violation {
+                        rule: Rule {
+                            before: page,
+                            after: later_page,
+                        },
+                        before_index,
+                        after_index,
+                    });
+                }
+            }
+        }
+
+        violations.sort_by_key(|violation| (violation.before_index, violation.after_index));
+
+        violations
+    }
+
+    /// Explains why an update is rejected, one violated rule per line.
+    pub fn violation_report(&self, update: &[u16]) -> String {
+        let pages = update
+            .iter()
+            .map(u16::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        let violations = self.violations(update);
+
+        if violations.is_empty() {
+            return format!("{pages} is valid\n");
+        }
+
+        let mut report = format!("{pages} breaks {} rule(s):\n", violations.len());
+        for violation in violations {
+            report.push_str(&format!("  {violation}\n"));
+        }
+
+        report
+    }
+}
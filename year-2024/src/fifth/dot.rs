@@ -0,0 +1,133 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+use super::{PageOrderingRules, Rule};
+
+/// What `to_dot` should draw.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DotOptions<'a> {
+    /// Only draw these pages and the rules between them, instead of all pages.
+    pub pages: Option<&'a [u16]>,
+    /// Draw the rules this update breaks in red.
+    pub highlighted_update: Option<&'a [u16]>,
+    /// Leave out rules that follow from others, e.g. 1|3 when there are 1|2 and 2|3. Highlighted
+    /// rules are always drawn. Cyclic rules have no unique reduction and are drawn completely.
+    pub transitive_reduction: bool,
+}
+
+impl PageOrderingRules {
+    /// Writes the rules as a Graphviz graph, with an edge from `before` to `after` for each rule.
+    pub fn to_dot(&self, options: DotOptions) -> String {
+        let pages = match options.pages {
+            Some(pages) => {
+                let mut pages = pages.to_vec();
+                pages.sort_unstable();
+                pages.dedup();
+                pages
+            }
+            None => self.pages(),
+        };
+        let page_set = pages.iter().copied().collect::<HashSet<_>>();
+
+        let mut rules = pages
+            .iter()
+            .flat_map(|&before| {
+                self.pages_after(before)
+                    .iter()
+                    .filter(|after| page_set.contains(after))
+                    .map(move |&after| Rule { before, after })
+            })
+            .collect::<Vec<_>>();
+        rules.sort_unstable();
+
+        let highlighted_rules = options
+            .highlighted_update
+            .map(|update| {
+                self.violations(update)
+                    .into_iter()
+                    .map(|violation| violation.rule)
+                    .collect::<HashSet<_>>()
+            })
+            .unwrap_or_default();
+
+        let mut dot = String::from("digraph rules {\n");
+
+        if options.transitive_reduction {
+            match transitive_reduction(&rules) {
+                Some(reduced_rules) => {
+                    rules.retain(|rule| {
+                        reduced_rules.contains(rule) || highlighted_rules.contains(rule)
+                    });
+                }
+                None => dot.push_str("    // the rules are cyclic, so they are drawn completely\n"),
+            }
+        }
+
+        for page in &pages {
+            writeln!(dot, "    {page};").unwrap();
+        }
+
+        for rule in &rules {
+            if highlighted_rules.contains(rule) {
+                writeln!(
+                    dot,
+                    "    {} -> {} [color=red, penwidth=2];",
+                    rule.before, rule.after
+                )
+                .unwrap();
+            } else {
+                writeln!(dot, "    {} -> {};", rule.before, rule.after).unwrap();
+            }
+        }
+
+        dot.push_str("}\n");
+
+        dot
+    }
+}
+
+/// The rules that don't follow from other rules, None if the rules are cyclic.
+fn transitive_reduction(rules: &[Rule]) -> Option<HashSet<Rule>> {
+    let mut pages_after: HashMap<u16, Vec<u16>> = HashMap::new();
+    for rule in rules {
+        pages_after.entry(rule.before).or_default().push(rule.after);
+    }
+
+    // every page that can be reached from each page by following one or more rules
+    let mut reachable: HashMap<u16, HashSet<u16>> = HashMap::new();
+    for &page in pages_after.keys() {
+        let mut reached = HashSet::new();
+        let mut stack = pages_after[&page].clone();
+
+        while let Some(next_page) = stack.pop() {
+            if reached.insert(next_page) {
+                stack.extend(pages_after.get(&next_page).into_iter().flatten());
+            }
+        }
+
+        if reached.contains(&page) {
+            return None;
+        }
+
+        reachable.insert(page, reached);
+    }
+
+    let is_implied = |rule: &Rule| {
+        pages_after[&rule.before].iter().any(|&other_page| {
+            other_page != rule.after
+                && reachable
+                    .get(&other_page)
+                    .is_some_and(|reached| reached.contains(&rule.after))
+        })
+    };
+
+    Some(
+        rules
+            .iter()
+            .filter(|rule| !is_implied(rule))
+            .copied()
+            .collect(),
+    )
+}
@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+
+use super::{page_positions, PageOrderingRules, Rule};
+
+impl PageOrderingRules {
+    /// Adds a rule, returns false if it already existed.
+    pub fn insert_rule(&mut self, rule: Rule) -> bool {
+        if self.has_rule(rule.before, rule.after) {
+            return false;
+        }
+
+        self.rules.entry(rule.before).or_default().push(rule.after);
+
+        true
+    }
+
+    /// Removes a rule, returns false if there was no such rule.
+    pub fn remove_rule(&mut self, rule: Rule) -> bool {
+        let Some(pages_after) = self.rules.get_mut(&rule.before) else {
+            return false;
+        };
+        let Some(i) = pages_after.iter().position(|&page| page == rule.after) else {
+            return false;
+        };
+
+        pages_after.remove(i);
+        if pages_after.is_empty() {
+            self.rules.remove(&rule.before);
+        }
+
+        true
+    }
+
+    /// Whether there is a rule between the two pages, in either direction.
+    #[cfg(test)]
+    pub fn is_ordered(&self, a: u16, b: u16) -> bool {
+        self.has_rule(a, b) || self.has_rule(b, a)
+    }
+}
+
+/// Rules together with updates, keeping track of which updates are valid while rules are inserted
+/// and removed. A rule change only looks at the updates that contain both of its pages.
+pub struct UpdateChecker {
+    rules: PageOrderingRules,
+    updates: Vec<Vec<u16>>,
+    positions: Vec<HashMap<u16, usize>>,
+    /// The indices of the updates each page is in.
+    updates_by_page: HashMap<u16, Vec<usize>>,
+    /// Number of rules each update breaks, it is valid when there are none.
+    violation_counts: Vec<usize>,
+}
+
+impl UpdateChecker {
+    /// # Panics
+    /// When an update contains a page more than once, a rule could then be broken by several of
+    /// its copies.
+    pub fn new(rules: PageOrderingRules, updates: Vec<Vec<u16>>) -> Self {
+        let positions = updates
+            .iter()
+            .map(|update| page_positions(update))
+            .collect::<Vec<_>>();
+
+        for (update, positions) in updates.iter().zip(&positions) {
+            assert_eq!(
+                positions.len(),
+                update.len(),
+                "Update {update:?} contains a page more than once"
+            );
+        }
+
+        let violation_counts = updates
+            .iter()
+            .map(|update| rules.violations(update).len())
+            .collect();
+
+        let mut updates_by_page: HashMap<u16, Vec<usize>> = HashMap::new();
+        for (i, update) in updates.iter().enumerate() {
+            for &page in update {
+                updates_by_page.entry(page).or_default().push(i);
+            }
+        }
+
+        Self {
+            rules,
+            updates,
+            positions,
+            updates_by_page,
+            violation_counts,
+        }
+    }
+
+    pub fn rules(&self) -> &PageOrderingRules {
+        &self.rules
+    }
+
+    pub fn updates(&self) -> &[Vec<u16>] {
+        &self.updates
+    }
+
+    pub fn is_valid(&self, update_index: usize) -> bool {
+        self.violation_counts[update_index] == 0
+    }
+
+    /// Indices of the currently valid updates.
+    pub fn valid_updates(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.updates.len()).filter(|&i| self.is_valid(i))
+    }
+
+    /// Adds a rule and returns the indices of the updates that were valid before, but aren't
+    /// anymore.
+    pub fn insert_rule(&mut self, rule: Rule) -> Vec<usize> {
+        if !self.rules.insert_rule(rule) {
+            return Vec::new();
+        }
+
+        let mut changed_updates = Vec::new();
+
+        for i in self.updates_breaking(rule) {
+            if self.is_valid(i) {
+                changed_updates.push(i);
+            }
+            self.violation_counts[i] += 1;
+        }
+
+        changed_updates
+    }
+
+    /// Removes a rule and returns the indices of the updates that weren't valid before, but are
+    /// now.
+    pub fn remove_rule(&mut self, rule: Rule) -> Vec<usize> {
+        if !self.rules.remove_rule(rule) {
+            return Vec::new();
+        }
+
+        let mut changed_updates = Vec::new();
+
+        for i in self.updates_breaking(rule) {
+            self.violation_counts[i] -= 1;
+            if self.is_valid(i) {
+                changed_updates.push(i);
+            }
+        }
+
+        changed_updates
+    }
+
+    /// The updates that contain both pages of the rule, in the wrong order (or the page of a rule
+    /// that puts it before itself).
+    fn updates_breaking(&self, rule: Rule) -> Vec<usize> {
+        let (Some(with_before), Some(with_after)) = (
+            self.updates_by_page.get(&rule.before),
+            self.updates_by_page.get(&rule.after),
+        ) else {
+            return Vec::new();
+        };
+
+        // go through the shorter list, the other page is looked up
+        let candidates = if with_before.len() <= with_after.len() {
+            with_before
+        } else {
+            with_after
+        };
+
+        candidates
+            .iter()
+            .copied()
+            .filter(|&i| {
+                let positions = &self.positions[i];
+
+                match (positions.get(&rule.before), positions.get(&rule.after)) {
+                    (Some(before_index), Some(after_index)) => after_index <= before_index,
+                    _ => false,
+                }
+            })
+            .collect()
+    }
+}
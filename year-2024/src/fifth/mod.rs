@@ -1,4 +1,22 @@
-use std::collections::HashMap;
+use bitset::BitsetRules;
+use cycles::RuleCycle;
+use dot::DotOptions;
+use editing::UpdateChecker;
+use parsing::parse_input;
+use std::{collections::HashMap, fmt, str::FromStr};
+
+#[cfg(test)]
+mod benches;
+mod bitset;
+mod cycles;
+mod diagnostics;
+mod dot;
+mod editing;
+mod moves;
+mod order;
+mod parsing;
+#[cfg(test)]
+mod tests;
 
 #[allow(unused)]
 const SAMPLE: &str = "47|53
@@ -30,13 +48,59 @@ const SAMPLE: &str = "47|53
 61,13,29
 97,13,75,29,47";
 
+/// `before` must be printed before `after` if an update contains both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct Rule {
+    before: u16,
+    after: u16,
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}|{}", self.before, self.after)
+    }
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (before, after) = s
+            .split_once('|')
+            .ok_or_else(|| format!("missing | in rule {s:?}"))?;
+
+        let parse_page = |page: &str| {
+            page.trim()
+                .parse()
+                .map_err(|_| format!("invalid page {page:?} in rule {s:?}"))
+        };
+
+        Ok(Rule {
+            before: parse_page(before)?,
+            after: parse_page(after)?,
+        })
+    }
+}
+
+/// What validating updates needs from a representation of the rules, either `PageOrderingRules`
+/// or the faster `BitsetRules` for small page numbers.
+trait OrderingRules {
+    /// Whether there is a rule that `before` must be printed before `after`.
+    fn has_rule(&self, before: u16, after: u16) -> bool;
+
+    fn is_valid_update(&self, update: &[u16]) -> bool;
+}
+
 #[derive(Debug)]
 struct PageOrderingRules {
     rules: HashMap<u16, Vec<u16>>,
 }
 
 impl PageOrderingRules {
-    pub fn is_allowed_update(&self, update: &Vec<u16>) -> (bool, Option<Vec<u16>>) {
+    /// Fixes an update by swapping the first two pages in the wrong order until it is allowed. Can
+    /// take a very long time, use `fix_update` instead. Only kept around to compare the results.
+    #[cfg(test)]
+    pub fn is_allowed_update(&self, update: &[u16]) -> (bool, Option<Vec<u16>>) {
         let mut already_printed_pages = Vec::new();
 
         for (i, page) in update.iter().enumerate() {
@@ -44,9 +108,7 @@ impl PageOrderingRules {
                 // check if we already printed something we are not allowed to
                 for (j, already_printed_page) in already_printed_pages.iter().enumerate() {
                     if must_not_be_printed_before.contains(already_printed_page) {
-                        // eprintln!("{already_printed_page} must not be printed before {page}");
-
-                        let mut fixed_update = update.clone();
+                        let mut fixed_update = update.to_vec();
                         fixed_update.swap(i, j);
 
                         let (is_allowed, maybe_fixed) = self.is_allowed_update(&fixed_update);
@@ -65,52 +127,287 @@ impl PageOrderingRules {
 
         (true, None)
     }
+
+    pub fn is_valid_update(&self, update: &[u16]) -> bool {
+        let positions = page_positions(update);
+
+        update.iter().enumerate().all(|(i, page)| {
+            self.pages_after(*page)
+                .iter()
+                .all(|later_page| positions.get(later_page).is_none_or(|&j| j > i))
+        })
+    }
+
+    /// Orders the pages of an update with a topological sort of the rules between them, in time
+    /// linear in the number of those rules. Going through the update in order, each page is printed
+    /// right after the pages that have to come before it, which are printed the same way first. So
+    /// a valid update stays as it is, and a page is only moved forward to where the first page that
+    /// needs it was. Returns None if the rules between the pages are contradictory.
+    pub fn fix_update(&self, update: &[u16]) -> Option<Vec<u16>> {
+        let positions = page_positions(update);
+
+        // indices of the pages that have to be printed before each page, in the order of the update
+        let mut pages_before = vec![Vec::new(); update.len()];
+
+        for (i, page) in update.iter().enumerate() {
+            for later_page in self.pages_after(*page) {
+                if let Some(&j) = positions.get(later_page) {
+                    pages_before[j].push(i);
+                }
+            }
+        }
+
+        let mut printed = vec![false; update.len()];
+        // pages on the stack, which wait for the pages before them
+        let mut waiting = vec![false; update.len()];
+        let mut fixed_update = Vec::with_capacity(update.len());
+
+        for first in 0..update.len() {
+            if printed[first] {
+                continue;
+            }
+
+            // a waiting page, and how many of the pages before it were looked at already
+            let mut stack = vec![(first, 0)];
+            waiting[first] = true;
+
+            while let Some((i, checked)) = stack.pop() {
+                let Some(&j) = pages_before[i].get(checked) else {
+                    waiting[i] = false;
+                    printed[i] = true;
+                    fixed_update.push(update[i]);
+                    continue;
+                };
+
+                stack.push((i, checked + 1));
+
+                if waiting[j] {
+                    // j has to come before i, which has to come before j
+                    return None;
+                }
+                if !printed[j] {
+                    waiting[j] = true;
+                    stack.push((j, 0));
+                }
+            }
+        }
+
+        Some(fixed_update)
+    }
+
+    /// All pages that appear in a rule, sorted.
+    fn pages(&self) -> Vec<u16> {
+        let mut pages = self
+            .rules
+            .iter()
+            .flat_map(|(&before, afters)| std::iter::once(before).chain(afters.iter().copied()))
+            .collect::<Vec<_>>();
+
+        pages.sort_unstable();
+        pages.dedup();
+
+        pages
+    }
+
+    /// Whether there is a rule that `before` must be printed before `after`.
+    pub fn has_rule(&self, before: u16, after: u16) -> bool {
+        self.pages_after(before).contains(&after)
+    }
+
+    /// The pages that must not be printed before `page`.
+    fn pages_after(&self, page: u16) -> &[u16] {
+        self.rules.get(&page).map_or(&[], Vec::as_slice)
+    }
+}
+
+impl OrderingRules for PageOrderingRules {
+    fn has_rule(&self, before: u16, after: u16) -> bool {
+        PageOrderingRules::has_rule(self, before, after)
+    }
+
+    fn is_valid_update(&self, update: &[u16]) -> bool {
+        PageOrderingRules::is_valid_update(self, update)
+    }
+}
+
+fn count_valid_updates(rules: &impl OrderingRules, updates: &[Vec<u16>]) -> usize {
+    updates
+        .iter()
+        .filter(|update| rules.is_valid_update(update))
+        .count()
+}
+
+fn page_positions(update: &[u16]) -> HashMap<u16, usize> {
+    update
+        .iter()
+        .enumerate()
+        .map(|(i, &page)| (page, i))
+        .collect()
 }
 
 pub(crate) fn fifth_december() {
     let input = include_str!("fifth.txt");
     // let input = SAMPLE;
 
-    let end_of_page_ordering_rules_section = input.find("\n\n").unwrap();
+    let (rules, updates) = parse_input(input).unwrap();
 
-    let page_ordering_rules_section = &input[0..=end_of_page_ordering_rules_section];
-    let rules = parse_page_ordering_rules(page_ordering_rules_section);
-    let mut sum_of_mid_pages = 0;
-    let mut sum_of_fixed_mid_pages = 0;
+    let (sum_of_mid_pages, sum_of_fixed_mid_pages) = sums_of_middle_pages(&rules, &updates);
 
-    for update in input[end_of_page_ordering_rules_section..].trim().lines() {
-        let update = update
-            .split(',')
-            .map(|x| x.parse::<u16>().unwrap())
-            .collect();
+    dbg!(sum_of_mid_pages);
+    dbg!(sum_of_fixed_mid_pages);
 
-        let (is_allowed, maybe_fixed_update) = rules.is_allowed_update(&update);
+    let page_moves = updates
+        .iter()
+        .filter_map(|update| rules.min_page_moves(update))
+        .sum::<usize>();
+    let adjacent_swaps = updates
+        .iter()
+        .filter_map(|update| rules.min_adjacent_swaps(update))
+        .sum::<usize>();
+    let ambiguous_updates = updates
+        .iter()
+        .filter(|update| rules.count_valid_orderings(update) > 1)
+        .count();
 
-        if is_allowed {
-            let mid_page = update[update.len() / 2];
-            sum_of_mid_pages += mid_page;
-        } else if let Some(fixed_update) = maybe_fixed_update {
-            let mid_page = fixed_update[fixed_update.len() / 2];
-            sum_of_fixed_mid_pages += mid_page;
+    dbg!(page_moves, adjacent_swaps, ambiguous_updates);
+
+    // the bitset only holds small pages, larger ones are fine for the puzzle but don't fit
+    match BitsetRules::try_from(&rules) {
+        Ok(bitset_rules) => {
+            dbg!(
+                count_valid_updates(&rules, &updates),
+                count_valid_updates(&bitset_rules, &updates)
+            );
+        }
+        Err(error) => {
+            dbg!(count_valid_updates(&rules, &updates), error);
         }
     }
 
-    dbg!(sum_of_mid_pages);
-    dbg!(sum_of_fixed_mid_pages);
-}
+    let cyclic_component_sizes = rules
+        .cyclic_components()
+        .iter()
+        .map(Vec::len)
+        .collect::<Vec<_>>();
+    let shortest_cycle = rules
+        .cycles()
+        .into_iter()
+        .min_by_key(|cycle| cycle.0.len())
+        .as_ref()
+        .map(RuleCycle::to_string);
+    let contradictory_updates = updates
+        .iter()
+        .filter_map(|update| rules.find_cycle_among(update))
+        .map(|cycle| cycle.to_string())
+        .collect::<Vec<_>>();
 
-fn parse_page_ordering_rules(input: impl AsRef<str>) -> PageOrderingRules {
-    let input = input.as_ref();
+    dbg!(
+        cyclic_component_sizes,
+        shortest_cycle,
+        contradictory_updates
+    );
+    let totally_ordered_updates = updates
+        .iter()
+        .filter(|update| rules.check_total_order(update).is_ok())
+        .count();
+    let all_pages_order = match rules.check_total_order(&rules.pages()) {
+        Ok(()) => "total order".to_string(),
+        Err(violations) => format!(
+            "{} missing pairs, {} contradicting pairs, {} non-transitive triples",
+            violations.missing_pairs.len(),
+            violations.contradicting_pairs.len(),
+            violations.non_transitive_triples.len()
+        ),
+    };
 
-    let mut rules: HashMap<u16, Vec<u16>> = HashMap::new();
+    // part 2 once more by sorting, with `compare` where the rules order all pages of the update
+    let sum_of_sorted_mid_pages = updates
+        .iter()
+        .filter(|update| !rules.is_valid_update(update))
+        .filter_map(|update| {
+            let mut sorted = update.clone();
+
+            if rules.check_total_order(update).is_ok() {
+                sorted.sort_by(|a, b| rules.compare(a, b));
+            } else {
+                sorted.sort_by(rules.comparator_for(update)?);
+            }
 
-    for rule in input.lines() {
-        let (before, after) = rule.split_at(rule.find('|').unwrap());
+            Some(u32::from(sorted[sorted.len() / 2]))
+        })
+        .sum::<u32>();
 
-        let (before, after) = (before.parse().unwrap(), after[1..].parse().unwrap()); // ignore separation character
+    dbg!(
+        totally_ordered_updates,
+        all_pages_order,
+        sum_of_sorted_mid_pages
+    );
 
-        rules.entry(before).or_default().push(after);
+    // `--dot` draws all rules, `--dot=<i>` only the ones between the pages of the i-th update
+    for argument in std::env::args() {
+        if argument == "--dot" {
+            print!("{}", rules.to_dot(DotOptions::default()));
+        } else if let Some(index) = argument.strip_prefix("--dot=") {
+            let update = &updates[index.parse::<usize>().unwrap()];
+
+            print!(
+                "{}",
+                rules.to_dot(DotOptions {
+                    pages: Some(update),
+                    highlighted_update: Some(update),
+                    transitive_reduction: true,
+                })
+            );
+        }
+    }
+
+    // what-if exploration: `--remove-rule=47|53` and `--insert-rule=53|47` can be repeated
+    let mut checker = UpdateChecker::new(rules, updates);
+    let mut rules_changed = false;
+
+    for argument in std::env::args() {
+        if let Some(rule) = argument.strip_prefix("--remove-rule=") {
+            let rule = rule.parse().unwrap();
+            let now_valid_updates = checker.remove_rule(rule);
+            dbg!(rule, now_valid_updates);
+            rules_changed = true;
+        } else if let Some(rule) = argument.strip_prefix("--insert-rule=") {
+            let rule = rule.parse().unwrap();
+            let now_invalid_updates = checker.insert_rule(rule);
+            dbg!(rule, now_invalid_updates);
+            rules_changed = true;
+        }
+    }
+
+    if rules_changed {
+        dbg!(checker.valid_updates().count());
+    }
+
+    let (rules, updates) = (checker.rules(), checker.updates());
+
+    if std::env::args().any(|argument| argument == "--explain-updates") {
+        for update in updates {
+            if !rules.is_valid_update(update) {
+                print!("{}", rules.violation_report(update));
+            }
+        }
+    }
+}
+
+/// Sums up the middle pages of the valid updates and of the invalid updates after fixing them.
+fn sums_of_middle_pages(rules: &PageOrderingRules, updates: &[Vec<u16>]) -> (u32, u32) {
+    let mut sum_of_mid_pages = 0;
+    let mut sum_of_fixed_mid_pages = 0;
+
+    for update in updates {
+        if rules.is_valid_update(update) {
+            let mid_page = update[update.len() / 2];
+            sum_of_mid_pages += u32::from(mid_page);
+        } else if let Some(fixed_update) = rules.fix_update(update) {
+            let mid_page = fixed_update[fixed_update.len() / 2];
+            sum_of_fixed_mid_pages += u32::from(mid_page);
+        }
     }
 
-    PageOrderingRules { rules }
+    (sum_of_mid_pages, sum_of_fixed_mid_pages)
 }
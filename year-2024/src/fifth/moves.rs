@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+
+use super::{page_positions, PageOrderingRules};
+
+impl PageOrderingRules {
+    /// The least number of pages that have to be taken out and put back somewhere else to make the
+    /// update valid, None if the rules between its pages are contradictory.
+    ///
+    /// The pages that stay where they are keep their order, so no two of them may be ones that the
+    /// rules, followed transitively, put the other way around. Any such set of pages can stay while
+    /// the others are moved into place one by one.
+    ///
+    /// Two pages the wrong way around are in a partial order: if the page at i must come after the
+    /// one at j > i, which must come after the one at k > j, then the one at i must come after the
+    /// one at k as well. The pages that stay are an antichain of it, and by Dilworth's theorem the
+    /// largest antichain leaves as many pages to move as there are pairs in a maximum matching
+    /// between earlier and later pages of wrongly ordered pairs.
+    pub fn min_page_moves(&self, update: &[u16]) -> Option<usize> {
+        let must_precede = self.transitive_rules_between(update)?;
+
+        // wrongly_after[i] are the later indices whose pages must come before the page at i
+        let wrongly_after = (0..update.len())
+            .map(|i| {
+                (i + 1..update.len())
+                    .filter(|&j| must_precede[j][i])
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let mut matched_earlier_index = vec![None; update.len()];
+        let mut matching_size = 0;
+
+        for i in 0..update.len() {
+            let mut visited = vec![false; update.len()];
+
+            if find_augmenting_path(i, &wrongly_after, &mut matched_earlier_index, &mut visited) {
+                matching_size += 1;
+            }
+        }
+
+        Some(matching_size)
+    }
+
+    /// The least number of swaps of neighbouring pages that make the update valid, None if the
+    /// rules between its pages are contradictory.
+    ///
+    /// Reaching an order with adjacent swaps takes as many swaps as there are pairs it puts the
+    /// other way around, so this is the order following the rules with the fewest such pairs.
+    ///
+    /// # Panics
+    /// When the update has more than 64 pages.
+    pub fn min_adjacent_swaps(&self, update: &[u16]) -> Option<usize> {
+        self.walk_orderings(
+            update,
+            0,
+            |swaps, mask, i| {
+                // the pages before i that aren't printed yet will end up after it
+                swaps + (!mask & ((1 << i) - 1)).count_ones() as usize
+            },
+            usize::min,
+        )
+    }
+
+    /// The number of different orders of the update's pages that follow the rules. Zero if the
+    /// rules between its pages are contradictory.
+    ///
+    /// # Panics
+    /// When the update has more than 64 pages or the number doesn't fit into `u128`.
+    pub fn count_valid_orderings(&self, update: &[u16]) -> u128 {
+        self.walk_orderings(
+            update,
+            1u128,
+            |orderings, _, _| orderings,
+            |a, b| a.checked_add(b).expect("Too many orderings to count"),
+        )
+        .unwrap_or(0)
+    }
+
+    /// Builds up the valid orders of the update one page at a time, with a value per set of pages
+    /// printed so far. `extend` computes the value after printing the page at index `i` from the
+    /// value before and the bitmask of the indices printed before, `combine` merges the values of
+    /// the different ways to reach the same set. Returns the value of the complete set of pages,
+    /// None if it can't be reached because the rules are contradictory.
+    ///
+    /// Only sets that contain every page that must be printed before one of their pages are ever
+    /// reached, which are few when the rules almost order the pages completely.
+    fn walk_orderings<T: Copy>(
+        &self,
+        update: &[u16],
+        initial: T,
+        extend: impl Fn(T, u64, usize) -> T,
+        combine: impl Fn(T, T) -> T,
+    ) -> Option<T> {
+        assert!(
+            update.len() <= 64,
+            "Can't order {} pages, at most 64 are supported",
+            update.len()
+        );
+
+        let positions = page_positions(update);
+
+        // bitmask of the indices of the pages that must be printed before each page
+        let mut required_before = vec![0u64; update.len()];
+
+        for (i, page) in update.iter().enumerate() {
+            for later_page in self.pages_after(*page) {
+                if let Some(&j) = positions.get(later_page) {
+                    required_before[j] |= 1 << i;
+                }
+            }
+        }
+
+        // all sets of the same size, starting with nothing printed
+        let mut printed_sets = HashMap::from([(0u64, initial)]);
+
+        for _ in 0..update.len() {
+            let mut next_printed_sets = HashMap::new();
+
+            for (&mask, &value) in &printed_sets {
+                for (i, &required) in required_before.iter().enumerate() {
+                    if mask & (1 << i) == 0 && required & !mask == 0 {
+                        let next_value = extend(value, mask, i);
+
+                        next_printed_sets
+                            .entry(mask | (1 << i))
+                            .and_modify(|known_value| {
+                                *known_value = combine(*known_value, next_value)
+                            })
+                            .or_insert(next_value);
+                    }
+                }
+            }
+
+            printed_sets = next_printed_sets;
+        }
+
+        printed_sets.into_values().next()
+    }
+
+    /// `must_precede[i][j]` tells whether the rules, followed transitively through the pages of the
+    /// update, put the page at index i before the one at index j. None if the rules are
+    /// contradictory.
+    fn transitive_rules_between(&self, update: &[u16]) -> Option<Vec<Vec<bool>>> {
+        let order = self.fix_update(update)?;
+        let positions = page_positions(update);
+        let mut must_precede = vec![vec![false; update.len()]; update.len()];
+
+        // the pages after a page are known completely once the ones after it in the order are
+        for page in order.iter().rev() {
+            let i = positions[page];
+
+            for later_page in self.pages_after(*page) {
+                if let Some(&j) = positions.get(later_page) {
+                    let pages_after_later_page = must_precede[j].clone();
+
+                    must_precede[i][j] = true;
+                    for (precedes, &precedes_through_j) in
+                        must_precede[i].iter_mut().zip(&pages_after_later_page)
+                    {
+                        *precedes |= precedes_through_j;
+                    }
+                }
+            }
+        }
+
+        Some(must_precede)
+    }
+}
+
+/// Kuhn's algorithm: tries to match the earlier index `i` with one of the later indices it is
+/// wrongly ordered with, taking a later index away from its earlier index if that one can be
+/// matched differently.
+fn find_augmenting_path(
+    i: usize,
+    wrongly_after: &[Vec<usize>],
+    matched_earlier_index: &mut [Option<usize>],
+    visited: &mut [bool],
+) -> bool {
+    for &j in &wrongly_after[i] {
+        if visited[j] {
+            continue;
+        }
+        visited[j] = true;
+
+        let can_be_taken = match matched_earlier_index[j] {
+            None => true,
+            Some(other) => {
+                find_augmenting_path(other, wrongly_after, matched_earlier_index, visited)
+            }
+        };
+
+        if can_be_taken {
+            matched_earlier_index[j] = Some(i);
+            return true;
+        }
+    }
+
+    false
+}
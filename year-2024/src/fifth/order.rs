@@ -0,0 +1,111 @@
+use std::{cmp::Ordering, collections::HashMap, fmt};
+
+use super::PageOrderingRules;
+
+/// Why the rules don't form a strict total order on a set of pages.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TotalOrderViolations {
+    /// Pairs of different pages without a rule between them.
+    pub missing_pairs: Vec<(u16, u16)>,
+    /// Pairs of pages with rules in both directions, or a page that must come before itself.
+    pub contradicting_pairs: Vec<(u16, u16)>,
+    /// `(a, b, c)` where `a` comes before `b` and `b` before `c`, but `a` not before `c`.
+    pub non_transitive_triples: Vec<(u16, u16, u16)>,
+}
+
+impl TotalOrderViolations {
+    pub fn is_empty(&self) -> bool {
+        self.missing_pairs.is_empty()
+            && self.contradicting_pairs.is_empty()
+            && self.non_transitive_triples.is_empty()
+    }
+}
+
+impl fmt::Display for TotalOrderViolations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (a, b) in &self.missing_pairs {
+            writeln!(f, "no rule between {a} and {b}")?;
+        }
+
+        for (a, b) in &self.contradicting_pairs {
+            if a == b {
+                writeln!(f, "{a}|{a} puts {a} before itself")?;
+            } else {
+                writeln!(f, "both {a}|{b} and {b}|{a}")?;
+            }
+        }
+
+        for (a, b, c) in &self.non_transitive_triples {
+            writeln!(f, "{a}|{b} and {b}|{c}, but not {a}|{c}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl PageOrderingRules {
+    /// Orders two pages by the rule between them, pages without a rule between them (or with
+    /// contradicting rules) are equal. Only sorts correctly when `check_total_order` accepts the
+    /// pages, otherwise use `comparator_for`.
+    pub fn compare(&self, a: &u16, b: &u16) -> Ordering {
+        match (self.has_rule(*a, *b), self.has_rule(*b, *a)) {
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            _ => Ordering::Equal,
+        }
+    }
+
+    /// A comparator that sorts the pages of this update into an order that follows the rules, even
+    /// when they only partially order the pages. None if the rules between the pages contradict
+    /// each other.
+    pub fn comparator_for(&self, update: &[u16]) -> Option<impl Fn(&u16, &u16) -> Ordering> {
+        let ranks = self
+            .fix_update(update)?
+            .into_iter()
+            .enumerate()
+            .map(|(rank, page)| (page, rank))
+            .collect::<HashMap<_, _>>();
+
+        Some(move |a: &u16, b: &u16| ranks.get(a).cmp(&ranks.get(b)))
+    }
+
+    /// Checks whether the rules between the pages form a strict total order, which is when sorting
+    /// with `compare` is safe. Pages are checked in the given order.
+    pub fn check_total_order(&self, pages: &[u16]) -> Result<(), TotalOrderViolations> {
+        let mut violations = TotalOrderViolations::default();
+
+        for (i, &a) in pages.iter().enumerate() {
+            if self.has_rule(a, a) {
+                violations.contradicting_pairs.push((a, a));
+            }
+
+            for &b in &pages[i + 1..] {
+                match (self.has_rule(a, b), self.has_rule(b, a)) {
+                    (false, false) => violations.missing_pairs.push((a, b)),
+                    (true, true) => violations.contradicting_pairs.push((a, b)),
+                    _ => {}
+                }
+            }
+        }
+
+        for &a in pages {
+            for &b in pages {
+                if a == b || !self.has_rule(a, b) {
+                    continue;
+                }
+
+                for &c in pages {
+                    if c != a && c != b && self.has_rule(b, c) && !self.has_rule(a, c) {
+                        violations.non_transitive_triples.push((a, b, c));
+                    }
+                }
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use super::{PageOrderingRules, Rule};
+
+/// A line of the input that couldn't be parsed, numbered from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Numbered lines without surrounding whitespace, so that it doesn't matter whether the lines end
+/// with `\n` or `\r\n`.
+fn numbered_lines(input: &str) -> impl Iterator<Item = (usize, &str)> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+}
+
+/// Parses the rules section and the updates section, which are separated by one or more blank
+/// lines. Blank lines before, after and within the updates are skipped.
+pub(super) fn parse_input(input: &str) -> Result<(PageOrderingRules, Vec<Vec<u16>>), ParseError> {
+    let mut lines = numbered_lines(input).skip_while(|(_, line)| line.is_empty());
+
+    let rules = parse_rule_lines(lines.by_ref().take_while(|(_, line)| !line.is_empty()))?;
+
+    let updates = lines
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(number, line)| {
+            parse_update(line).map_err(|message| ParseError {
+                line: number,
+                message,
+            })
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok((rules, updates))
+}
+
+/// Parses rules like `47|53`, one per line. Blank lines are skipped.
+#[cfg(test)]
+pub(super) fn parse_page_ordering_rules(
+    input: impl AsRef<str>,
+) -> Result<PageOrderingRules, ParseError> {
+    parse_rule_lines(numbered_lines(input.as_ref()).filter(|(_, line)| !line.is_empty()))
+}
+
+fn parse_rule_lines<'a>(
+    lines: impl Iterator<Item = (usize, &'a str)>,
+) -> Result<PageOrderingRules, ParseError> {
+    let mut rules: HashMap<u16, Vec<u16>> = HashMap::new();
+
+    for (number, line) in lines {
+        let rule = line.parse::<Rule>().map_err(|message| ParseError {
+            line: number,
+            message,
+        })?;
+
+        rules.entry(rule.before).or_default().push(rule.after);
+    }
+
+    Ok(PageOrderingRules { rules })
+}
+
+/// Parses comma separated pages. Updates need a middle page, so they can't have an even number of
+/// pages, and each page is printed once.
+fn parse_update(line: &str) -> Result<Vec<u16>, String> {
+    let update = line
+        .split(',')
+        .map(|page| {
+            page.trim()
+                .parse()
+                .map_err(|_| format!("invalid page {page:?} in update {line:?}"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if update.len() % 2 == 0 {
+        return Err(format!(
+            "update {line:?} has {} pages, so it has no middle page",
+            update.len()
+        ));
+    }
+
+    let mut pages = HashSet::new();
+    if let Some(page) = update.iter().find(|&&page| !pages.insert(page)) {
+        return Err(format!("update {line:?} repeats page {page}"));
+    }
+
+    Ok(update)
+}
@@ -0,0 +1,760 @@
+use std::{collections::HashSet, time::Instant};
+
+use itertools::Itertools;
+
+use crate::pseudo_random::PseudoRandom;
+
+use super::{
+    bitset::BitsetRules,
+    cycles::RuleCycle,
+    diagnostics::Violation,
+    dot::DotOptions,
+    editing::UpdateChecker,
+    order::TotalOrderViolations,
+    parsing::{parse_input, parse_page_ordering_rules, ParseError},
+    sums_of_middle_pages, OrderingRules, Rule, SAMPLE,
+};
+
+const INPUT: &str = include_str!("fifth.txt");
+
+#[test]
+fn test_sample() {
+    let (rules, updates) = parse_input(SAMPLE).unwrap();
+    assert_eq!(sums_of_middle_pages(&rules, &updates), (143, 123));
+}
+
+#[test]
+fn test_input() {
+    let (rules, updates) = parse_input(INPUT).unwrap();
+    assert_eq!(sums_of_middle_pages(&rules, &updates), (5713, 5180));
+}
+
+#[test]
+fn test_fix_update() {
+    let (rules, _) = parse_input(SAMPLE).unwrap();
+
+    assert_eq!(
+        rules.fix_update(&[75, 97, 47, 61, 53]),
+        Some(vec![97, 75, 47, 61, 53])
+    );
+    assert_eq!(rules.fix_update(&[61, 13, 29]), Some(vec![61, 29, 13]));
+    assert_eq!(
+        rules.fix_update(&[97, 13, 75, 29, 47]),
+        Some(vec![97, 75, 47, 29, 13])
+    );
+
+    // valid updates stay as they are
+    assert_eq!(
+        rules.fix_update(&[75, 47, 61, 53, 29]),
+        Some(vec![75, 47, 61, 53, 29])
+    );
+
+    // pages without rules between them keep their order, 97 is only moved in front of 13
+    assert_eq!(rules.fix_update(&[5, 13, 4, 97]), Some(vec![5, 97, 13, 4]));
+    assert_eq!(rules.fix_update(&[4, 53, 5, 75]), Some(vec![4, 75, 53, 5]));
+}
+
+#[test]
+fn test_fix_update_matches_swapping_fixer() {
+    let (rules, updates) = parse_input(INPUT).unwrap();
+
+    for update in updates {
+        let (is_allowed, swapped) = rules.is_allowed_update(&update);
+        assert_eq!(is_allowed, rules.is_valid_update(&update), "{update:?}");
+
+        if is_allowed {
+            assert_eq!(rules.fix_update(&update).as_ref(), Some(&update));
+        } else {
+            let fixed = rules.fix_update(&update).unwrap();
+            assert!(rules.is_valid_update(&fixed));
+            // the rules between the pages of each update are a total order, so there is only
+            // one valid order
+            assert_eq!(Some(fixed), swapped, "{update:?}");
+        }
+    }
+}
+
+#[test]
+fn test_fix_update_on_reversed_chain() {
+    // every page has to come before all pages with a higher number, and the update is reversed
+    let pages = 0..400;
+    let rules = pages
+        .clone()
+        .flat_map(|before| (before + 1..400).map(move |after| format!("{before}|{after}")))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let rules = parse_page_ordering_rules(rules).unwrap();
+
+    let update = pages.clone().rev().collect::<Vec<_>>();
+    assert!(!rules.is_valid_update(&update));
+
+    let instant = Instant::now();
+    let fixed = rules.fix_update(&update).unwrap();
+    dbg!(instant.elapsed());
+
+    assert_eq!(fixed, pages.collect::<Vec<_>>());
+}
+
+#[test]
+fn test_fix_update_with_cycle() {
+    let rules = parse_page_ordering_rules("47|53\n53|29\n29|47\n29|13").unwrap();
+
+    assert_eq!(rules.fix_update(&[13, 29, 53, 47]), None);
+    assert_eq!(rules.fix_update(&[13, 29, 53]), Some(vec![53, 29, 13]));
+}
+
+#[test]
+fn test_cycles() {
+    let (rules, updates) = parse_input(SAMPLE).unwrap();
+    assert!(rules.cyclic_components().is_empty());
+    assert!(rules.cycles().is_empty());
+    assert!(updates
+        .iter()
+        .all(|update| rules.find_cycle_among(update).is_none()));
+
+    let rules = parse_page_ordering_rules("47|53\n53|29\n29|47\n29|13\n13|5\n5|5").unwrap();
+    assert_eq!(rules.cyclic_components(), [vec![5], vec![29, 47, 53]]);
+
+    let cycle = rules.find_cycle_among(&[47, 53, 29, 13]).unwrap();
+    assert_eq!(
+        cycle,
+        RuleCycle(vec![
+            Rule {
+                before: 47,
+                after: 53
+            },
+            Rule {
+                before: 53,
+                after: 29
+            },
+            Rule {
+                before: 29,
+                after: 47
+            },
+        ])
+    );
+    assert_eq!(cycle.to_string(), "47|53, 53|29, 29|47");
+    assert_eq!(
+        rules
+            .cycles()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>(),
+        ["5|5", "29|47, 47|53, 53|29"]
+    );
+
+    // without 29 the rules between the pages can be ordered
+    assert_eq!(rules.find_cycle_among(&[47, 53, 13]), None);
+}
+
+#[test]
+fn test_shortest_cycle_is_found() {
+    // 1 -> 2 -> 3 -> 4 -> 1 and the shortcut 2 -> 4
+    let rules = parse_page_ordering_rules("1|2\n2|3\n3|4\n4|1\n2|4").unwrap();
+
+    assert_eq!(
+        rules.find_cycle_among(&[1, 2, 3, 4]).unwrap().to_string(),
+        "1|2, 2|4, 4|1"
+    );
+}
+
+#[test]
+fn test_cycles_in_input() {
+    let (rules, updates) = parse_input(INPUT).unwrap();
+
+    // all pages are in one big cycle, but the rules within every update can be ordered
+    let components = rules.cyclic_components();
+    assert_eq!(components.len(), 1);
+    assert_eq!(components[0], rules.pages());
+    assert_eq!(rules.cycles()[0].0.len(), 3);
+
+    assert!(updates
+        .iter()
+        .all(|update| rules.find_cycle_among(update).is_none()));
+}
+
+#[test]
+fn test_violations() {
+    let (rules, updates) = parse_input(SAMPLE).unwrap();
+
+    assert!(rules.violations(&updates[0]).is_empty());
+    assert_eq!(
+        rules.violations(&updates[3]),
+        [Violation {
+            rule: Rule {
+                before: 97,
+                after: 75
+            },
+            before_index: 1,
+            after_index: 0,
+        }]
+    );
+    assert_eq!(
+        rules.violation_report(&updates[3]),
+        "75,97,47,61,53 breaks 1 rule(s):\n  \
+         97 at index 1 must come before 75 at index 0 (rule 97|75 reversed)\n"
+    );
+
+    assert_eq!(
+        rules.violation_report(&updates[4]),
+        "61,13,29 breaks 1 rule(s):\n  \
+         29 at index 2 must come before 13 at index 1 (rule 29|13 reversed)\n"
+    );
+    assert_eq!(rules.violations(&updates[5]).len(), 4);
+    assert_eq!(
+        rules.violation_report(&updates[0]),
+        "75,47,61,53,29 is valid\n"
+    );
+
+    // a page that must come before itself can't be printed at all
+    let rules = parse_page_ordering_rules("78|78").unwrap();
+    assert!(!rules.is_valid_update(&[12, 78]));
+    assert_eq!(
+        rules.violation_report(&[12, 78]),
+        "12,78 breaks 1 rule(s):\n  78 at index 1 must come before 78 at index 1 (rule 78|78 reversed)\n"
+    );
+}
+
+#[test]
+fn test_violations_match_validity() {
+    let (rules, updates) = parse_input(INPUT).unwrap();
+
+    for update in &updates {
+        let violations = rules.violations(update);
+        assert_eq!(violations.is_empty(), rules.is_valid_update(update));
+
+        for violation in violations {
+            assert_eq!(update[violation.before_index], violation.rule.before);
+            assert_eq!(update[violation.after_index], violation.rule.after);
+            assert!(violation.after_index < violation.before_index);
+        }
+
+        // the fixed update doesn't break any rule anymore
+        let fixed = rules.fix_update(update).unwrap();
+        assert!(rules.violations(&fixed).is_empty());
+    }
+}
+
+#[test]
+fn test_compare() {
+    let (rules, updates) = parse_input(SAMPLE).unwrap();
+
+    // the sample rules order all of its pages
+    assert_eq!(rules.check_total_order(&rules.pages()), Ok(()));
+
+    for update in updates {
+        let mut sorted = update.clone();
+        sorted.sort_by(|a, b| rules.compare(a, b));
+
+        assert_eq!(Some(sorted), rules.fix_update(&update));
+    }
+}
+
+#[test]
+fn test_compare_on_input() {
+    let (rules, updates) = parse_input(INPUT).unwrap();
+
+    for update in updates {
+        assert_eq!(rules.check_total_order(&update), Ok(()), "{update:?}");
+
+        let mut sorted = update.clone();
+        sorted.sort_by(|a, b| rules.compare(a, b));
+        assert_eq!(Some(sorted), rules.fix_update(&update));
+    }
+
+    // taken together, the rules contradict each other
+    let violations = rules.check_total_order(&rules.pages()).unwrap_err();
+    assert!(violations.missing_pairs.is_empty());
+    assert!(violations.contradicting_pairs.is_empty());
+    assert!(!violations.non_transitive_triples.is_empty());
+}
+
+#[test]
+fn test_partial_order() {
+    // 1 before 2 and 3, nothing between 2 and 3
+    let rules = parse_page_ordering_rules("1|2\n1|3\n4|5\n5|6\n6|4\n7|8\n8|7\n9|9").unwrap();
+
+    assert_eq!(
+        rules.check_total_order(&[3, 2, 1]),
+        Err(TotalOrderViolations {
+            missing_pairs: vec![(3, 2)],
+            ..Default::default()
+        })
+    );
+
+    let update = [3, 2, 1];
+    let comparator = rules.comparator_for(&update).unwrap();
+    let mut sorted = update.to_vec();
+    sorted.sort_by(comparator);
+    assert_eq!(sorted, [1, 3, 2]);
+
+    let violations = rules.check_total_order(&[4, 5, 6]).unwrap_err();
+    assert_eq!(
+        violations.non_transitive_triples,
+        [(4, 5, 6), (5, 6, 4), (6, 4, 5)]
+    );
+    assert!(rules.comparator_for(&[4, 5, 6]).is_none());
+
+    let violations = rules.check_total_order(&[7, 8, 9]).unwrap_err();
+    assert_eq!(violations.contradicting_pairs, [(7, 8), (9, 9)]);
+    assert_eq!(
+        violations.to_string(),
+        "no rule between 7 and 9\n\
+         no rule between 8 and 9\n\
+         both 7|8 and 8|7\n\
+         9|9 puts 9 before itself\n"
+    );
+}
+
+#[test]
+fn test_dot() {
+    let rules = parse_page_ordering_rules("1|2\n2|3\n1|3\n3|4").unwrap();
+
+    assert_eq!(
+        rules.to_dot(DotOptions::default()),
+        "digraph rules {\n    1;\n    2;\n    3;\n    4;\n    1 -> 2;\n    1 -> 3;\n    2 -> 3;\n    3 -> 4;\n}\n"
+    );
+
+    // 1|3 follows from 1|2 and 2|3
+    assert_eq!(
+        rules.to_dot(DotOptions {
+            transitive_reduction: true,
+            ..Default::default()
+        }),
+        "digraph rules {\n    1;\n    2;\n    3;\n    4;\n    1 -> 2;\n    2 -> 3;\n    3 -> 4;\n}\n"
+    );
+
+    // without 2, 1|3 is needed
+    assert_eq!(
+        rules.to_dot(DotOptions {
+            pages: Some(&[3, 1, 4]),
+            transitive_reduction: true,
+            ..Default::default()
+        }),
+        "digraph rules {\n    1;\n    3;\n    4;\n    1 -> 3;\n    3 -> 4;\n}\n"
+    );
+
+    // violated rules are highlighted and kept even when they follow from others
+    assert_eq!(
+        rules.to_dot(DotOptions {
+            pages: Some(&[3, 2, 1]),
+            highlighted_update: Some(&[3, 2, 1]),
+            transitive_reduction: true,
+        }),
+        "digraph rules {\n    1;\n    2;\n    3;\n    \
+         1 -> 2 [color=red, penwidth=2];\n    \
+         1 -> 3 [color=red, penwidth=2];\n    \
+         2 -> 3 [color=red, penwidth=2];\n}\n"
+    );
+}
+
+#[test]
+fn test_dot_of_sample() {
+    let (rules, updates) = parse_input(SAMPLE).unwrap();
+
+    // the sample rules are a total order, which reduces to a chain
+    let dot = rules.to_dot(DotOptions {
+        transitive_reduction: true,
+        ..Default::default()
+    });
+    assert_eq!(dot.matches("->").count(), 6);
+    for rule in [
+        "97 -> 75;",
+        "75 -> 47;",
+        "47 -> 61;",
+        "61 -> 53;",
+        "53 -> 29;",
+        "29 -> 13;",
+    ] {
+        assert!(dot.contains(rule), "{rule} missing in {dot}");
+    }
+
+    let dot = rules.to_dot(DotOptions {
+        pages: Some(&updates[3]),
+        highlighted_update: Some(&updates[3]),
+        transitive_reduction: true,
+    });
+    assert!(dot.contains("97 -> 75 [color=red, penwidth=2];"));
+    assert_eq!(dot.matches("->").count(), 4);
+
+    // cyclic rules can't be reduced
+    let rules = parse_page_ordering_rules("47|53\n53|29\n29|47\n47|29").unwrap();
+    let dot = rules.to_dot(DotOptions {
+        transitive_reduction: true,
+        ..Default::default()
+    });
+    assert!(dot.contains("// the rules are cyclic"));
+    assert_eq!(dot.matches("->").count(), 4);
+}
+
+#[test]
+fn test_edit_rules() {
+    let (mut rules, _) = parse_input(SAMPLE).unwrap();
+    let rule = Rule {
+        before: 97,
+        after: 75,
+    };
+
+    assert!(rules.is_ordered(75, 97));
+    assert!(rules.remove_rule(rule));
+    assert!(!rules.remove_rule(rule));
+    assert!(!rules.is_ordered(75, 97));
+
+    assert!(rules.insert_rule(rule));
+    assert!(!rules.insert_rule(rule));
+    assert!(rules.has_rule(97, 75));
+}
+
+#[test]
+fn test_parse_rule() {
+    assert_eq!(
+        "47|53".parse(),
+        Ok(Rule {
+            before: 47,
+            after: 53
+        })
+    );
+    assert!("47-53".parse::<Rule>().is_err());
+    assert!("47|x".parse::<Rule>().is_err());
+}
+
+#[test]
+fn test_update_checker() {
+    let (rules, updates) = parse_input(SAMPLE).unwrap();
+    let mut checker = UpdateChecker::new(rules, updates);
+
+    assert_eq!(checker.valid_updates().collect::<Vec<_>>(), [0, 1, 2]);
+
+    // 75,97,47,61,53 only breaks 97|75
+    let rule = Rule {
+        before: 97,
+        after: 75,
+    };
+    assert_eq!(checker.remove_rule(rule), [3]);
+    assert_eq!(checker.valid_updates().collect::<Vec<_>>(), [0, 1, 2, 3]);
+    assert_eq!(checker.remove_rule(rule), []);
+
+    assert_eq!(checker.insert_rule(rule), [3]);
+    assert!(!checker.is_valid(3));
+
+    // 75,29,13 breaks a new rule
+    assert_eq!(
+        checker.insert_rule(Rule {
+            before: 13,
+            after: 75
+        }),
+        [2]
+    );
+}
+
+#[test]
+#[should_panic(expected = "contains a page more than once")]
+fn test_update_checker_rejects_repeated_pages() {
+    let rules = parse_page_ordering_rules("2|1").unwrap();
+
+    UpdateChecker::new(rules, vec![vec![1, 2, 2]]);
+}
+
+#[test]
+fn test_update_checker_matches_rechecking() {
+    let (rules, updates) = parse_input(INPUT).unwrap();
+    let pages = rules.pages();
+    let mut checker = UpdateChecker::new(rules, updates);
+
+    let mut random = PseudoRandom::new(3);
+    let mut next_page = || pages[random.below(pages.len())];
+
+    for round in 0..200 {
+        let rule = Rule {
+            before: next_page(),
+            after: next_page(),
+        };
+        let was_valid = (0..checker.updates().len())
+            .map(|i| checker.is_valid(i))
+            .collect::<Vec<_>>();
+
+        let changed_updates = if round % 2 == 0 {
+            checker.remove_rule(rule)
+        } else {
+            checker.insert_rule(rule)
+        };
+
+        for (i, update) in checker.updates().iter().enumerate() {
+            let is_valid = checker.rules().is_valid_update(update);
+
+            assert_eq!(checker.is_valid(i), is_valid, "{rule} in round {round}");
+            assert_eq!(changed_updates.contains(&i), was_valid[i] != is_valid);
+        }
+    }
+}
+
+#[test]
+fn test_min_page_moves() {
+    let (rules, updates) = parse_input(SAMPLE).unwrap();
+
+    let moves = updates
+        .iter()
+        .map(|update| rules.min_page_moves(update))
+        .collect::<Vec<_>>();
+    assert_eq!(moves, [0, 0, 0, 1, 1, 2].map(Some));
+
+    let rules = parse_page_ordering_rules("29|47\n47|13\n").unwrap();
+    assert_eq!(rules.min_page_moves(&[13, 29, 47]), Some(1));
+    // 29 and 13 have no rule between them, but 29|47 and 47|13 put 29 first anyway, so they
+    // can't both stay
+    assert_eq!(rules.min_page_moves(&[13, 47, 29]), Some(2));
+    assert_eq!(rules.min_page_moves(&[47, 13, 29]), Some(1));
+
+    let rules = parse_page_ordering_rules("1|2\n2|1\n").unwrap();
+    assert_eq!(rules.min_page_moves(&[1, 2]), None);
+
+    // 1 and 2 as well as 2 and 3 may stay, but not all three
+    let rules = parse_page_ordering_rules("3|1\n").unwrap();
+    assert!(!rules.is_valid_update(&[1, 2, 3]));
+    assert_eq!(rules.min_page_moves(&[1, 2, 3]), Some(1));
+}
+
+#[test]
+fn test_min_page_moves_matches_brute_force() {
+    let mut random = PseudoRandom::new(11);
+
+    for _ in 0..300 {
+        // partial rules between the pages 1 to 6, which may contain cycles
+        let mut rules = parse_page_ordering_rules("").unwrap();
+        for a in 1..=6 {
+            for b in a + 1..=6 {
+                match random.below(6) {
+                    0 => rules.insert_rule(Rule {
+                        before: a,
+                        after: b,
+                    }),
+                    1 => rules.insert_rule(Rule {
+                        before: b,
+                        after: a,
+                    }),
+                    _ => false,
+                };
+            }
+        }
+
+        let mut update = (1..=6).collect::<Vec<u16>>();
+        for i in (1..update.len()).rev() {
+            update.swap(i, random.below(i + 1));
+        }
+        update.truncate(2 + random.below(5));
+
+        // the most pages of the update that any valid order keeps in the same order
+        let most_kept_pages = update
+            .iter()
+            .copied()
+            .permutations(update.len())
+            .filter(|order| rules.is_valid_update(order))
+            .map(|order| longest_common_subsequence(&update, &order))
+            .max();
+
+        assert_eq!(
+            rules.min_page_moves(&update),
+            most_kept_pages.map(|kept_pages| update.len() - kept_pages),
+            "{update:?}"
+        );
+    }
+}
+
+fn longest_common_subsequence(a: &[u16], b: &[u16]) -> usize {
+    let mut lengths = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            lengths[i + 1][j + 1] = if x == y {
+                lengths[i][j] + 1
+            } else {
+                lengths[i][j + 1].max(lengths[i + 1][j])
+            };
+        }
+    }
+
+    lengths[a.len()][b.len()]
+}
+
+#[test]
+fn test_min_adjacent_swaps() {
+    let (rules, updates) = parse_input(SAMPLE).unwrap();
+
+    let swaps = updates
+        .iter()
+        .map(|update| rules.min_adjacent_swaps(update))
+        .collect::<Vec<_>>();
+    assert_eq!(swaps, [0, 0, 0, 1, 1, 4].map(Some));
+
+    // 3 isn't ordered against the others, so it can stay where it is
+    let rules = parse_page_ordering_rules("1|2\n").unwrap();
+    assert_eq!(rules.min_adjacent_swaps(&[2, 1, 3]), Some(1));
+    assert_eq!(rules.min_adjacent_swaps(&[2, 3, 1]), Some(2));
+    assert_eq!(rules.min_adjacent_swaps(&[3, 2, 1]), Some(1));
+
+    let rules = parse_page_ordering_rules("1|2\n2|3\n3|1\n").unwrap();
+    assert_eq!(rules.min_adjacent_swaps(&[1, 2, 3]), None);
+}
+
+#[test]
+fn test_min_adjacent_swaps_matches_brute_force() {
+    let rules = parse_page_ordering_rules("1|2\n1|3\n4|3\n5|2\n3|6\n").unwrap();
+    let update = [6, 3, 2, 5, 4, 1];
+
+    // breadth first search over all orders of the update, one adjacent swap at a time
+    let mut seen = HashSet::from([update.to_vec()]);
+    let mut frontier = vec![update.to_vec()];
+    let mut swaps = 0;
+
+    while !frontier.iter().any(|order| rules.is_valid_update(order)) {
+        let mut next_frontier = Vec::new();
+
+        for order in frontier {
+            for i in 1..order.len() {
+                let mut swapped = order.clone();
+                swapped.swap(i - 1, i);
+
+                if seen.insert(swapped.clone()) {
+                    next_frontier.push(swapped);
+                }
+            }
+        }
+
+        frontier = next_frontier;
+        swaps += 1;
+    }
+
+    assert_eq!(rules.min_adjacent_swaps(&update), Some(swaps));
+}
+
+#[test]
+fn test_count_valid_orderings() {
+    let (rules, updates) = parse_input(SAMPLE).unwrap();
+
+    // the sample rules order every update completely
+    for update in &updates {
+        assert_eq!(rules.count_valid_orderings(update), 1);
+    }
+
+    let rules = parse_page_ordering_rules("").unwrap();
+    assert_eq!(rules.count_valid_orderings(&[1, 2, 3, 4]), 24);
+    assert_eq!(
+        rules.count_valid_orderings(&(1..=12).collect::<Vec<_>>()),
+        479_001_600
+    );
+
+    // 1 first, 4 last, 2 and 3 either way round
+    let rules = parse_page_ordering_rules("1|2\n1|3\n2|4\n3|4\n").unwrap();
+    assert_eq!(rules.count_valid_orderings(&[4, 3, 2, 1]), 2);
+
+    let rules = parse_page_ordering_rules("1|2\n2|1\n").unwrap();
+    assert_eq!(rules.count_valid_orderings(&[1, 2, 3]), 0);
+}
+
+#[test]
+fn test_bitset_rules() {
+    for input in [SAMPLE, INPUT] {
+        let (rules, updates) = parse_input(input).unwrap();
+        let bitset_rules = BitsetRules::try_from(&rules).unwrap();
+
+        for update in &updates {
+            assert_eq!(
+                OrderingRules::is_valid_update(&bitset_rules, update),
+                rules.is_valid_update(update),
+                "{update:?}"
+            );
+        }
+
+        for before in rules.pages() {
+            for after in rules.pages() {
+                assert_eq!(
+                    OrderingRules::has_rule(&bitset_rules, before, after),
+                    rules.has_rule(before, after)
+                );
+            }
+        }
+    }
+
+    let mut bitset_rules = BitsetRules::default();
+    assert_eq!(bitset_rules.insert_rule("1|2".parse().unwrap()), Ok(true));
+    assert_eq!(bitset_rules.insert_rule("1|2".parse().unwrap()), Ok(false));
+    assert!(bitset_rules.insert_rule("1|128".parse().unwrap()).is_err());
+
+    // pages that don't fit can't be in a rule, so they never make an update invalid
+    assert!(bitset_rules.is_valid_update(&[1, 500, 2, 1000]));
+    assert!(!bitset_rules.is_valid_update(&[2, 500, 1]));
+
+    assert_eq!(bitset_rules.insert_rule("3|3".parse().unwrap()), Ok(true));
+    assert!(!bitset_rules.is_valid_update(&[3]));
+
+    let rules = parse_page_ordering_rules("1|200\n").unwrap();
+    assert!(BitsetRules::try_from(&rules).is_err());
+}
+
+#[test]
+fn test_parse_input_with_crlf_and_blank_lines() {
+    let expected = parse_input(SAMPLE).unwrap();
+
+    for input in [
+        SAMPLE.replace('\n', "\r\n"),
+        format!("\n\n{SAMPLE}\n\n\n"),
+        SAMPLE.replace("\n\n", "\n\n\n\n"),
+        SAMPLE.replace("\n\n", "\r\n  \r\n\t\r\n"),
+        SAMPLE.replace(',', " , ").replace('|', " | "),
+    ] {
+        let (rules, updates) = parse_input(&input).unwrap();
+
+        assert_eq!(rules.rules, expected.0.rules);
+        assert_eq!(updates, expected.1);
+    }
+
+    let (rules, updates) = parse_input("").unwrap();
+    assert!(rules.rules.is_empty());
+    assert!(updates.is_empty());
+}
+
+#[test]
+fn test_parse_errors() {
+    let error = |line: usize, message: &str| {
+        Err(ParseError {
+            line,
+            message: message.to_string(),
+        })
+    };
+
+    assert_eq!(
+        parse_input("47|53\n97-13\n\n75,47,61").map(|_| ()),
+        error(2, "missing | in rule \"97-13\"")
+    );
+    assert_eq!(
+        parse_input("\r\n47|53\r\n97|x\r\n").map(|_| ()),
+        error(3, "invalid page \"x\" in rule \"97|x\"")
+    );
+    assert_eq!(
+        parse_input("47|53\n\n75,47,61\n\n47,5x,53").map(|_| ()),
+        error(5, "invalid page \"5x\" in update \"47,5x,53\"")
+    );
+    assert_eq!(
+        parse_input("47|53\n\n75,47,61\n47,53").map(|_| ()),
+        error(4, "update \"47,53\" has 2 pages, so it has no middle page")
+    );
+    assert_eq!(
+        parse_input("47|53\n\n75,,61").map(|_| ()),
+        error(3, "invalid page \"\" in update \"75,,61\"")
+    );
+    assert_eq!(
+        parse_input("47|53\n\n75,47,61\n\n47,61,47").map(|_| ()),
+        error(5, "update \"47,61,47\" repeats page 47")
+    );
+
+    // without a blank line, the updates are taken for rules
+    assert_eq!(
+        parse_input("47|53\n75,47,61").map(|_| ()),
+        error(2, "missing | in rule \"75,47,61\"")
+    );
+
+    assert_eq!(
+        parse_input("47|53\n97 13\n").unwrap_err().to_string(),
+        "line 2: missing | in rule \"97 13\""
+    );
+}
@@ -1,6 +1,9 @@
 mod fifth;
 mod first;
 mod fourth;
+mod progress;
+#[cfg(test)]
+mod pseudo_random;
 mod second;
 mod seventh;
 mod sixth;
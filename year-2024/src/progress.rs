@@ -0,0 +1,167 @@
+use std::{
+    io::Write,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Receives progress updates from long-running solvers. Parallel solvers call it from all of their
+/// workers at the same time.
+pub(crate) trait Progress: Sync {
+    fn start(&self, total: usize);
+
+    fn advance(&self, count: usize);
+
+    fn finish(&self);
+}
+
+/// Picks how to report progress for this run: a single updating line on stderr, or nothing at all
+/// when `--machine-output` is passed.
+pub(crate) fn progress_for(label: &'static str) -> Box<dyn Progress> {
+    if std::env::args().any(|argument| argument == "--machine-output") {
+        Box::new(SilentProgress)
+    } else {
+        Box::new(TerminalProgress::new(label))
+    }
+}
+
+pub(crate) struct SilentProgress;
+
+impl Progress for SilentProgress {
+    fn start(&self, _total: usize) {}
+
+    fn advance(&self, _count: usize) {}
+
+    fn finish(&self) {}
+}
+
+/// How often the progress line is redrawn at most.
+const RENDER_INTERVAL: Duration = Duration::from_millis(100);
+
+pub(crate) struct TerminalProgress {
+    label: &'static str,
+    total: AtomicUsize,
+    done: AtomicUsize,
+    /// When we started and when we last drew the line. Workers that find it locked skip drawing.
+    timing: Mutex<(Instant, Instant)>,
+}
+
+impl TerminalProgress {
+    pub(crate) fn new(label: &'static str) -> Self {
+        let now = Instant::now();
+
+        Self {
+            label,
+            total: AtomicUsize::new(0),
+            done: AtomicUsize::new(0),
+            timing: Mutex::new((now, now)),
+        }
+    }
+
+    fn render(&self, started: Instant) {
+        let line = render_line(
+            self.label,
+            self.done.load(Ordering::Relaxed),
+            self.total.load(Ordering::Relaxed),
+            started.elapsed(),
+        );
+
+        let mut stderr = std::io::stderr().lock();
+        let _ = write!(stderr, "\r{line}\x1b[K");
+        let _ = stderr.flush();
+    }
+}
+
+impl Progress for TerminalProgress {
+    fn start(&self, total: usize) {
+        self.total.store(total, Ordering::Relaxed);
+        self.done.store(0, Ordering::Relaxed);
+
+        let now = Instant::now();
+        *self.timing.lock().unwrap() = (now, now);
+
+        self.render(now);
+    }
+
+    fn advance(&self, count: usize) {
+        self.done.fetch_add(count, Ordering::Relaxed);
+
+        let Ok(mut timing) = self.timing.try_lock() else {
+            // someone else is drawing right now
+            return;
+        };
+
+        let (started, last_rendered) = *timing;
+        if last_rendered.elapsed() < RENDER_INTERVAL {
+            return;
+        }
+
+        timing.1 = Instant::now();
+        self.render(started);
+    }
+
+    fn finish(&self) {
+        let (started, _) = *self.timing.lock().unwrap();
+
+        self.render(started);
+        eprintln!();
+    }
+}
+
+fn render_line(label: &str, done: usize, total: usize, elapsed: Duration) -> String {
+    let percentage = if total == 0 {
+        100.0
+    } else {
+        done as f64 / total as f64 * 100.0
+    };
+
+    let mut line = format!("{label}: {done}/{total} ({percentage:.0}%)");
+
+    let elapsed_seconds = elapsed.as_secs_f64();
+    if done > 0 && elapsed_seconds > 0.0 {
+        let throughput = done as f64 / elapsed_seconds;
+        line.push_str(&format!(", {throughput:.1}/s"));
+
+        if done < total {
+            let eta = Duration::from_secs_f64((total - done) as f64 / throughput);
+            line.push_str(&format!(", ETA {eta:.1?}"));
+        } else {
+            line.push_str(&format!(", took {elapsed:.1?}"));
+        }
+    }
+
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::render_line;
+
+    #[test]
+    fn renders_throughput_and_eta() {
+        assert_eq!(
+            render_line("seventh", 250, 1000, Duration::from_secs(2)),
+            "seventh: 250/1000 (25%), 125.0/s, ETA 6.0s"
+        );
+        assert_eq!(
+            render_line("seventh", 1000, 1000, Duration::from_millis(1500)),
+            "seventh: 1000/1000 (100%), 666.7/s, took 1.5s"
+        );
+    }
+
+    #[test]
+    fn renders_without_anything_done() {
+        assert_eq!(
+            render_line("sixth", 0, 130, Duration::ZERO),
+            "sixth: 0/130 (0%)"
+        );
+        assert_eq!(
+            render_line("sixth", 0, 0, Duration::ZERO),
+            "sixth: 0/0 (100%)"
+        );
+    }
+}
@@ -0,0 +1,22 @@
+/// Deterministic pseudo random numbers for tests and benchmarks, from a linear congruential
+/// generator. Good enough to shuffle test data, and every run sees the same numbers.
+pub(crate) struct PseudoRandom {
+    state: u64,
+}
+
+impl PseudoRandom {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// A number in `0..bound`.
+    pub(crate) fn below(&mut self, bound: usize) -> usize {
+        self.state = self
+            .state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+
+        // the high bits are the most random ones
+        (self.state >> 33) as usize % bound
+    }
+}
@@ -0,0 +1,42 @@
+use super::{is_valid_equation, number::Number, operators::OperatorSet, Equation};
+
+/// Works from the test value towards the first number by undoing the operators, starting with the
+/// last number. Most operators can only be undone for very few intermediate results (the result has
+/// to be divisible for a multiplication, end with the right digits for a concatenation), so most
+/// branches end right away.
+fn undo_operators<N: Number>(numbers: &[N], target: N, operators: &OperatorSet<N>) -> bool {
+    let Some((last_number, numbers)) = numbers.split_last() else {
+        panic!("equation without numbers");
+    };
+
+    if numbers.is_empty() {
+        return *last_number == target;
+    }
+
+    // all operators can be undone, see `is_valid_equation_backwards`
+    operators
+        .iter()
+        .filter_map(|operator| operator.as_invertible())
+        .any(|operator| {
+            operator
+                .unapply(target.clone(), last_number.clone())
+                .is_some_and(|previous_target| undo_operators(numbers, previous_target, operators))
+        })
+}
+
+/// Same as `is_valid_equation`, but searches from right to left. Falls back to searching from the
+/// left when an operator can't be undone or when there are zeros, which make undoing ambiguous.
+pub(super) fn is_valid_equation_backwards<N: Number>(
+    equation: &Equation<N>,
+    operators: &OperatorSet<N>,
+) -> bool {
+    if !operators
+        .iter()
+        .all(|operator| operator.as_invertible().is_some())
+        || equation.numbers.contains(&N::zero())
+    {
+        return is_valid_equation(equation, operators);
+    }
+
+    undo_operators(&equation.numbers, equation.test_value.clone(), operators)
+}
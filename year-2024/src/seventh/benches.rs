@@ -0,0 +1,96 @@
+// Timings of the equation solvers on the puzzle input and of their helpers. They are ignored by
+// default, run them with `cargo test --release -- --ignored --nocapture benches`.
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use super::{
+    backwards::is_valid_equation_backwards,
+    is_valid_equation,
+    operators::{ADD_AND_MULTIPLY, ADD_MULTIPLY_AND_CONCATENATE},
+    parse_equation_list,
+    permutations::{recursive::RecursiveUniquePermutations, UniquePermutations},
+    Equation, NumberType,
+};
+
+const ROUNDS: u32 = 20;
+
+fn time_solver(
+    equations: &[Equation],
+    solver: impl Fn(&Equation) -> bool,
+) -> (NumberType, Duration) {
+    let instant = Instant::now();
+    let mut sum = 0;
+
+    for _ in 0..ROUNDS {
+        sum = equations
+            .iter()
+            .filter(|equation| solver(equation))
+            .map(|equation| equation.test_value)
+            .sum();
+    }
+
+    (sum, instant.elapsed() / ROUNDS)
+}
+
+#[test]
+#[ignore]
+fn bench_forwards_against_backwards() {
+    let equations = parse_equation_list(include_str!("part7-input.txt"));
+
+    for operators in [ADD_AND_MULTIPLY, ADD_MULTIPLY_AND_CONCATENATE] {
+        let (forwards_sum, forwards_elapsed) = time_solver(&equations, |equation| {
+            is_valid_equation(equation, operators)
+        });
+        let (backwards_sum, backwards_elapsed) = time_solver(&equations, |equation| {
+            is_valid_equation_backwards(equation, operators)
+        });
+
+        dbg!(operators.len(), forwards_elapsed, backwards_elapsed);
+
+        assert_eq!(forwards_sum, backwards_sum);
+    }
+}
+
+#[test]
+#[ignore]
+fn bench_unique_permutations() {
+    let elements = vec![1, 1, 2, 2, 3, 3, 4, 4, 5, 6];
+
+    let instant = Instant::now();
+    let mut recursive_count = 0;
+    for _ in 0..ROUNDS {
+        recursive_count = RecursiveUniquePermutations::new(elements.clone()).count();
+    }
+    let recursive_elapsed = instant.elapsed() / ROUNDS;
+
+    let instant = Instant::now();
+    let mut in_place_count = 0;
+    for _ in 0..ROUNDS {
+        in_place_count = 0;
+        for permutation in UniquePermutations::new(elements.clone()) {
+            black_box(permutation);
+            in_place_count += 1;
+        }
+    }
+    let in_place_elapsed = instant.elapsed() / ROUNDS;
+
+    let instant = Instant::now();
+    let mut lending_count = 0;
+    for _ in 0..ROUNDS {
+        let mut permutations = UniquePermutations::new(elements.clone());
+        lending_count = 0;
+        while let Some(permutation) = permutations.next_slice() {
+            black_box(permutation);
+            lending_count += 1;
+        }
+    }
+    let lending_elapsed = instant.elapsed() / ROUNDS;
+
+    dbg!(recursive_elapsed, in_place_elapsed, lending_elapsed);
+
+    assert_eq!(recursive_count, in_place_count);
+    assert_eq!(recursive_count, lending_count);
+}
@@ -0,0 +1,360 @@
+//! Iterators over the ways to pick elements, e.g. all operator sequences of an equation. They all
+//! keep their current selection in a buffer that is updated in place, which can be borrowed with
+//! `next_slice` to walk through them without allocating.
+
+use super::permutations::next_permutation;
+
+/// Binomial coefficient n choose k, None if it doesn't fit into `usize`.
+fn binomial(n: usize, k: usize) -> Option<usize> {
+    if k > n {
+        return Some(0);
+    }
+
+    let k = k.min(n - k);
+    let mut result: usize = 1;
+
+    for i in 1..=k {
+        // result * (n - k + i) is divisible by i, as it is i * binomial(n - k + i, i)
+        result = result.checked_mul(n - k + i)? / i;
+    }
+
+    Some(result)
+}
+
+/// Arrangements of `k` elements out of a multiset, in lexicographic order. Equal elements are
+/// interchangeable, so every arrangement is yielded only once.
+pub(super) struct KPermutations<T> {
+    /// The first `k` elements are the current arrangement, the rest are in ascending order.
+    elements: Vec<T>,
+    k: usize,
+    /// None if there are too many arrangements to count.
+    remaining: Option<usize>,
+    started: bool,
+    finished: bool,
+}
+
+impl<T: Ord> KPermutations<T> {
+    /// # Panics
+    /// When `k` exceeds the number of elements.
+    pub(super) fn new(mut elements: Vec<T>, k: usize) -> Self {
+        assert!(
+            k <= elements.len(),
+            "Can't pick {k} out of {}",
+            elements.len()
+        );
+
+        elements.sort_unstable();
+        let remaining = count_k_permutations(&elements, k);
+
+        Self {
+            elements,
+            k,
+            remaining,
+            started: false,
+            finished: false,
+        }
+    }
+
+    pub(super) fn next_slice(&mut self) -> Option<&[T]> {
+        if self.finished {
+            return None;
+        }
+
+        if self.started {
+            // with the unused elements in descending order, the next permutation of everything
+            // is the one with the next prefix, and the unused elements in ascending order again
+            self.elements[self.k..].reverse();
+            if !next_permutation(&mut self.elements) {
+                self.finished = true;
+                return None;
+            }
+        }
+
+        self.started = true;
+        self.remaining = self.remaining.map(|remaining| remaining - 1);
+
+        Some(&self.elements[..self.k])
+    }
+}
+
+impl<T: Ord + Clone> Iterator for KPermutations<T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_slice().map(<[T]>::to_vec)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining.unwrap_or(usize::MAX), self.remaining)
+    }
+}
+
+/// Number of arrangements of length `k` out of the sorted `elements`, None if it doesn't fit into
+/// `usize`.
+fn count_k_permutations<T: Ord>(elements: &[T], k: usize) -> Option<usize> {
+    // arrangements[j] is the number of arrangements of length j using the groups of equal
+    // elements seen so far. Taking t copies of the next group means choosing t of the j + t places.
+    let mut arrangements = vec![0; k + 1];
+    arrangements[0] = 1;
+
+    for group in elements.chunk_by(|a, b| a == b) {
+        for j in (0..=k).rev() {
+            for t in 1..=group.len().min(j) {
+                let ways = binomial(j, t)?.checked_mul(arrangements[j - t])?;
+                arrangements[j] = arrangements[j].checked_add(ways)?;
+            }
+        }
+    }
+
+    Some(arrangements[k])
+}
+
+/// Selections of `k` elements in the order they are given, either taking every element at most
+/// once or allowing to take it repeatedly. Elements are told apart by their position, not by their
+/// value.
+pub(super) struct Combinations<T> {
+    elements: Vec<T>,
+    /// Positions of the current selection, ascending (or non-descending with repetition).
+    indices: Vec<usize>,
+    current: Vec<T>,
+    with_repetition: bool,
+    /// None if there are too many combinations to count.
+    remaining: Option<usize>,
+    started: bool,
+    finished: bool,
+}
+
+impl<T: Clone> Combinations<T> {
+    pub(super) fn new(elements: Vec<T>, k: usize) -> Self {
+        let remaining = binomial(elements.len(), k);
+
+        Self::with_indices(elements, (0..k).collect(), false, remaining)
+    }
+
+    pub(super) fn with_repetition(elements: Vec<T>, k: usize) -> Self {
+        let remaining = match elements.len() {
+            // nothing can be picked from nothing, except for nothing
+            0 => Some(usize::from(k == 0)),
+            n => binomial(n + k - 1, k),
+        };
+
+        Self::with_indices(elements, vec![0; k], true, remaining)
+    }
+
+    fn with_indices(
+        elements: Vec<T>,
+        indices: Vec<usize>,
+        with_repetition: bool,
+        remaining: Option<usize>,
+    ) -> Self {
+        let finished = remaining == Some(0);
+        let current = if finished {
+            Vec::new()
+        } else {
+            indices.iter().map(|&i| elements[i].clone()).collect()
+        };
+
+        Self {
+            elements,
+            indices,
+            current,
+            with_repetition,
+            remaining,
+            started: false,
+            finished,
+        }
+    }
+
+    pub(super) fn next_slice(&mut self) -> Option<&[T]> {
+        if self.finished {
+            return None;
+        }
+
+        if self.started {
+            let n = self.elements.len();
+            let k = self.indices.len();
+
+            // the rightmost position that hasn't reached its highest possible index yet
+            let Some(position) = (0..k).rposition(|position| {
+                let highest = if self.with_repetition {
+                    n - 1
+                } else {
+                    n - k + position
+                };
+                self.indices[position] < highest
+            }) else {
+                self.finished = true;
+                return None;
+            };
+
+            self.indices[position] += 1;
+
+            for next_position in position + 1..k {
+                self.indices[next_position] = if self.with_repetition {
+                    self.indices[position]
+                } else {
+                    self.indices[next_position - 1] + 1
+                };
+            }
+
+            for changed_position in position..k {
+                self.current[changed_position] =
+                    self.elements[self.indices[changed_position]].clone();
+            }
+        }
+
+        self.started = true;
+        self.remaining = self.remaining.map(|remaining| remaining - 1);
+
+        Some(&self.current)
+    }
+}
+
+impl<T: Clone> Iterator for Combinations<T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_slice().map(<[T]>::to_vec)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining.unwrap_or(usize::MAX), self.remaining)
+    }
+}
+
+/// In which order `CartesianPower` goes through the sequences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum SequenceOrder {
+    /// Like counting, the last position changes the fastest.
+    Lexicographic,
+    /// Reflected Gray code: consecutive sequences differ in exactly one position, by one step in
+    /// `elements`. Whatever was computed from the positions before that one can be kept.
+    GrayCode,
+}
+
+/// All sequences of `length` elements, each picked from `elements`, e.g. every way to place
+/// operators between the numbers of an equation.
+pub(super) struct CartesianPower<T> {
+    elements: Vec<T>,
+    order: SequenceOrder,
+    indices: Vec<usize>,
+    /// Whether the index at each position currently counts up, only used for Gray code order.
+    ascending: Vec<bool>,
+    current: Vec<T>,
+    /// None if there are too many sequences to count.
+    remaining: Option<usize>,
+    started: bool,
+    finished: bool,
+}
+
+impl<T: Clone> CartesianPower<T> {
+    pub(super) fn new(elements: Vec<T>, length: usize, order: SequenceOrder) -> Self {
+        let remaining = u32::try_from(length)
+            .ok()
+            .and_then(|length| elements.len().checked_pow(length));
+
+        let current = match elements.first() {
+            Some(first) => vec![first.clone(); length],
+            None => Vec::new(),
+        };
+
+        Self {
+            elements,
+            order,
+            indices: vec![0; length],
+            ascending: vec![true; length],
+            current,
+            remaining,
+            started: false,
+            finished: remaining == Some(0),
+        }
+    }
+
+    /// Lending version of `next`, which also returns the first position that changed compared to
+    /// the previous sequence, or None for the first sequence.
+    pub(super) fn next_change(&mut self) -> Option<(Option<usize>, &[T])> {
+        if self.finished {
+            return None;
+        }
+
+        let changed_position = if self.started {
+            let position = match self.order {
+                SequenceOrder::Lexicographic => self.advance_lexicographic(),
+                SequenceOrder::GrayCode => self.advance_gray_code(),
+            };
+            if position.is_none() {
+                self.finished = true;
+                return None;
+            }
+            position
+        } else {
+            None
+        };
+
+        self.started = true;
+        self.remaining = self.remaining.map(|remaining| remaining - 1);
+
+        Some((changed_position, &self.current))
+    }
+
+    pub(super) fn next_slice(&mut self) -> Option<&[T]> {
+        self.next_change().map(|(_, sequence)| sequence)
+    }
+
+    /// Moves on to the next sequence and returns the first position that changed, None if this
+    /// was the last one.
+    fn advance_lexicographic(&mut self) -> Option<usize> {
+        let last_index = self.elements.len().checked_sub(1)?;
+        let position = self.indices.iter().rposition(|&index| index < last_index)?;
+
+        self.indices[position] += 1;
+        self.current[position] = self.elements[self.indices[position]].clone();
+
+        for later_position in position + 1..self.indices.len() {
+            self.indices[later_position] = 0;
+            self.current[later_position] = self.elements[0].clone();
+        }
+
+        Some(position)
+    }
+
+    fn advance_gray_code(&mut self) -> Option<usize> {
+        let last_index = self.elements.len().checked_sub(1)?;
+
+        // the rightmost position that can still move in its direction, everything after it turns
+        let mut position = self.indices.len();
+        loop {
+            position = position.checked_sub(1)?;
+
+            let index = self.indices[position];
+            if (self.ascending[position] && index < last_index)
+                || (!self.ascending[position] && index > 0)
+            {
+                break;
+            }
+
+            self.ascending[position] = !self.ascending[position];
+        }
+
+        if self.ascending[position] {
+            self.indices[position] += 1;
+        } else {
+            self.indices[position] -= 1;
+        }
+        self.current[position] = self.elements[self.indices[position]].clone();
+
+        Some(position)
+    }
+}
+
+impl<T: Clone> Iterator for CartesianPower<T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_slice().map(<[T]>::to_vec)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining.unwrap_or(usize::MAX), self.remaining)
+    }
+}
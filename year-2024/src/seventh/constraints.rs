@@ -0,0 +1,172 @@
+use std::str::FromStr;
+
+use super::{
+    number::Number,
+    operators::{Operator, ALL_OPERATORS},
+};
+
+/// Restrictions on which operator sequences count as a solution. Operators are referred to by
+/// their name, which has to be one of `ALL_OPERATORS`, positions by the index of the gap between
+/// two numbers, starting at 0.
+///
+/// The description is a comma separated list of
+/// - `multiply=1`, `concatenate<=2` or `add>=1` to limit how often an operator is used,
+/// - `!multiply@0` to forbid an operator at a position,
+/// - `pattern=add multiply` to require the operators to appear next to each other somewhere.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(super) struct OperatorConstraints {
+    counts: Vec<CountConstraint>,
+    forbidden_positions: Vec<(String, usize)>,
+    required_patterns: Vec<Vec<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CountConstraint {
+    operator: String,
+    at_least: usize,
+    at_most: usize,
+}
+
+impl FromStr for OperatorConstraints {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut constraints = Self::default();
+
+        for clause in s
+            .split(',')
+            .map(str::trim)
+            .filter(|clause| !clause.is_empty())
+        {
+            if let Some(pattern) = clause.strip_prefix("pattern=") {
+                let pattern = pattern
+                    .split_whitespace()
+                    .map(|operator| operator_name(operator, clause))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                if pattern.is_empty() {
+                    return Err(format!("empty pattern in {clause:?}"));
+                }
+
+                constraints.required_patterns.push(pattern);
+            } else if let Some(forbidden) = clause.strip_prefix('!') {
+                let (operator, position) = forbidden
+                    .split_once('@')
+                    .ok_or_else(|| format!("missing position in {clause:?}"))?;
+                let position = position
+                    .parse()
+                    .map_err(|_| format!("invalid position in {clause:?}"))?;
+
+                constraints
+                    .forbidden_positions
+                    .push((operator_name(operator, clause)?, position));
+            } else {
+                let (operator, relation, count) = ["<=", ">=", "="]
+                    .into_iter()
+                    .find_map(|relation| {
+                        let (operator, count) = clause.split_once(relation)?;
+                        Some((operator, relation, count))
+                    })
+                    .ok_or_else(|| format!("unknown constraint {clause:?}"))?;
+                let count = count
+                    .parse()
+                    .map_err(|_| format!("invalid count in {clause:?}"))?;
+
+                let (at_least, at_most) = match relation {
+                    "<=" => (0, count),
+                    ">=" => (count, usize::MAX),
+                    _ => (count, count),
+                };
+
+                constraints.counts.push(CountConstraint {
+                    operator: operator_name(operator, clause)?,
+                    at_least,
+                    at_most,
+                });
+            }
+        }
+
+        Ok(constraints)
+    }
+}
+
+/// The operator name of a clause, which must belong to one of `ALL_OPERATORS`, so that a typo
+/// doesn't silently match nothing.
+fn operator_name(name: &str, clause: &str) -> Result<String, String> {
+    let name = name.trim();
+
+    if ALL_OPERATORS.iter().any(|operator| operator.name() == name) {
+        Ok(name.to_string())
+    } else {
+        Err(format!("unknown operator {name:?} in {clause:?}"))
+    }
+}
+
+impl OperatorConstraints {
+    /// Whether `chosen`, the operators for the first gaps of an equation with `gap_count` gaps,
+    /// can still be completed into a sequence that satisfies all constraints. Only the last chosen
+    /// operator is checked against the forbidden positions, the ones before were checked already.
+    pub(super) fn allows<N: Number>(&self, chosen: &[&dyn Operator<N>], gap_count: usize) -> bool {
+        let open_gaps = gap_count - chosen.len();
+
+        if let Some((last_operator, earlier_operators)) = chosen.split_last() {
+            let position = earlier_operators.len();
+
+            let is_forbidden =
+                self.forbidden_positions
+                    .iter()
+                    .any(|(operator, forbidden_position)| {
+                        *forbidden_position == position && operator == last_operator.name()
+                    });
+
+            if is_forbidden {
+                return false;
+            }
+        }
+
+        let counts_are_reachable = self.counts.iter().all(|constraint| {
+            let used = chosen
+                .iter()
+                .filter(|operator| operator.name() == constraint.operator)
+                .count();
+
+            used <= constraint.at_most && used + open_gaps >= constraint.at_least
+        });
+
+        counts_are_reachable
+            && self
+                .required_patterns
+                .iter()
+                .all(|pattern| can_still_contain(chosen, pattern, open_gaps))
+    }
+}
+
+/// Whether `pattern` appears in `chosen` already, or could appear once `open_gaps` more operators
+/// are appended.
+fn can_still_contain<N: Number>(
+    chosen: &[&dyn Operator<N>],
+    pattern: &[String],
+    open_gaps: usize,
+) -> bool {
+    let matches = |operators: &[&dyn Operator<N>], pattern: &[String]| {
+        operators
+            .iter()
+            .zip(pattern)
+            .all(|(operator, name)| operator.name() == name)
+    };
+
+    if chosen
+        .windows(pattern.len())
+        .any(|window| matches(window, pattern))
+    {
+        return true;
+    }
+
+    // the longest end of what we have that the pattern could continue from
+    let continued_length = (0..pattern.len().min(chosen.len() + 1))
+        .rev()
+        .find(|&length| matches(&chosen[chosen.len() - length..], &pattern[..length]))
+        .unwrap_or(0);
+
+    pattern.len() - continued_length <= open_gaps
+}
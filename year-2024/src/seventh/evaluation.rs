@@ -0,0 +1,368 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use super::{
+    can_prune, number::Number, operators::Operator, solution::find_solution, Equation, NumberType,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum EvaluationMode {
+    /// Strictly from left to right, as the puzzle asks for.
+    LeftToRight,
+    /// Conventional operator precedence as given by `Operator::precedence`.
+    Precedence,
+    /// Any binary expression tree over the numbers, which keep their order.
+    AnyParenthesisation,
+}
+
+impl FromStr for EvaluationMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "left-to-right" => Ok(Self::LeftToRight),
+            "precedence" => Ok(Self::Precedence),
+            "any-parenthesisation" => Ok(Self::AnyParenthesisation),
+            other => Err(format!("unknown evaluation mode {other:?}")),
+        }
+    }
+}
+
+pub(super) enum Expression<'a, N: Number = NumberType> {
+    Number(N),
+    Operation {
+        operator: &'a dyn Operator<N>,
+        left: Box<Expression<'a, N>>,
+        right: Box<Expression<'a, N>>,
+    },
+}
+
+impl<N: Number> Expression<'_, N> {
+    fn precedence(&self) -> Option<u8> {
+        match self {
+            Self::Number(_) => None,
+            Self::Operation { operator, .. } => Some(operator.precedence()),
+        }
+    }
+
+    pub(super) fn evaluate(&self) -> Option<N> {
+        match self {
+            Self::Number(number) => Some(number.clone()),
+            Self::Operation {
+                operator,
+                left,
+                right,
+            } => operator.apply(left.evaluate()?, right.evaluate()?),
+        }
+    }
+}
+
+/// Writes the expression with as few parentheses as needed when reading it with conventional
+/// operator precedence.
+impl<N: Number> fmt::Display for Expression<'_, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self::Operation {
+            operator,
+            left,
+            right,
+        } = self
+        else {
+            return write!(f, "{}", self.evaluate().unwrap());
+        };
+
+        let precedence = operator.precedence();
+
+        match left.precedence() {
+            Some(left_precedence) if left_precedence < precedence => write!(f, "({left})")?,
+            _ => write!(f, "{left}")?,
+        }
+
+        write!(f, " {} ", operator.symbol())?;
+
+        match right.precedence() {
+            Some(right_precedence) if right_precedence <= precedence => write!(f, "({right})"),
+            _ => write!(f, "{right}"),
+        }
+    }
+}
+
+impl<N: Number> fmt::Debug for Expression<'_, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+/// Combines the values with the operators between them, where operators of higher precedence are
+/// applied first (shunting-yard). Used to build the expression tree of a solution.
+fn combine_with_precedence<'a, T, N: Number>(
+    values: impl IntoIterator<Item = T>,
+    operators: &[&'a dyn Operator<N>],
+    mut combine: impl FnMut(&'a dyn Operator<N>, T, T) -> Option<T>,
+) -> Option<T> {
+    let mut values = values.into_iter();
+    let mut value_stack = vec![values.next()?];
+    let mut operator_stack: Vec<&dyn Operator<N>> = Vec::with_capacity(operators.len());
+
+    let mut reduce = |value_stack: &mut Vec<T>, operator| {
+        let right = value_stack.pop().unwrap();
+        let left = value_stack.pop().unwrap();
+        value_stack.push(combine(operator, left, right)?);
+
+        Some(())
+    };
+
+    for (&operator, value) in operators.iter().zip(values) {
+        while let Some(&pending_operator) = operator_stack.last() {
+            if pending_operator.precedence() < operator.precedence() {
+                break;
+            }
+
+            operator_stack.pop();
+            reduce(&mut value_stack, pending_operator)?;
+        }
+
+        operator_stack.push(operator);
+        value_stack.push(value);
+    }
+
+    while let Some(pending_operator) = operator_stack.pop() {
+        reduce(&mut value_stack, pending_operator)?;
+    }
+
+    value_stack.pop()
+}
+
+/// Applies the pending operators on top of the stack as long as they bind at least as tight as
+/// `precedence` (all of them for None). Returns None when a result is undefined.
+fn reduce_pending_operators<N: Number>(
+    values: &mut Vec<N>,
+    pending_operators: &mut Vec<&dyn Operator<N>>,
+    precedence: Option<u8>,
+) -> Option<()> {
+    while let Some(&pending_operator) = pending_operators.last() {
+        if precedence.is_some_and(|precedence| pending_operator.precedence() < precedence) {
+            break;
+        }
+
+        pending_operators.pop();
+        let right = values.pop().unwrap();
+        let left = values.pop().unwrap();
+        values.push(pending_operator.apply(left, right)?);
+    }
+
+    Some(())
+}
+
+/// Tries all operator sequences while evaluating them with precedence on the way (shunting-yard).
+/// Every value that has been reduced already is part of the final result, so when no operator
+/// decreases a value we can give up as soon as one of them exceeds the test value.
+fn search_with_precedence<'a, N: Number>(
+    equation: &Equation<N>,
+    operators: &[&'a dyn Operator<N>],
+    can_prune: bool,
+    (values, pending_operators): (&[N], &[&'a dyn Operator<N>]),
+    chosen_operators: &mut Vec<&'a dyn Operator<N>>,
+) -> bool {
+    let Some(next_number) = equation.numbers.get(chosen_operators.len() + 1) else {
+        let mut values = values.to_vec();
+        let mut pending_operators = pending_operators.to_vec();
+
+        return reduce_pending_operators(&mut values, &mut pending_operators, None).is_some()
+            && values == [equation.test_value.clone()];
+    };
+
+    for &operator in operators {
+        let mut next_values = values.to_vec();
+        let mut next_pending_operators = pending_operators.to_vec();
+
+        if reduce_pending_operators(
+            &mut next_values,
+            &mut next_pending_operators,
+            Some(operator.precedence()),
+        )
+        .is_none()
+        {
+            continue;
+        }
+
+        if can_prune && next_values.last() > Some(&equation.test_value) {
+            continue;
+        }
+
+        next_values.push(next_number.clone());
+        next_pending_operators.push(operator);
+        chosen_operators.push(operator);
+
+        if search_with_precedence(
+            equation,
+            operators,
+            can_prune,
+            (&next_values, &next_pending_operators),
+            chosen_operators,
+        ) {
+            return true;
+        }
+
+        chosen_operators.pop();
+    }
+
+    false
+}
+
+/// How the value of an expression over a range of numbers was produced.
+#[derive(Clone)]
+enum Derivation<'a, N: Number> {
+    Number,
+    Operation {
+        operator: &'a dyn Operator<N>,
+        /// Index of the last number of the left subexpression.
+        split: usize,
+        left: N,
+        right: N,
+    },
+}
+
+type Derivations<'a, N> = HashMap<N, Derivation<'a, N>>;
+
+fn build_expression<'a, N: Number>(
+    derivations: &[Vec<Derivations<'a, N>>],
+    (start, end): (usize, usize),
+    value: N,
+) -> Expression<'a, N> {
+    match &derivations[start][end][&value] {
+        Derivation::Number => Expression::Number(value),
+        Derivation::Operation {
+            operator,
+            split,
+            left,
+            right,
+        } => Expression::Operation {
+            operator: *operator,
+            left: Box::new(build_expression(derivations, (start, *split), left.clone())),
+            right: Box::new(build_expression(
+                derivations,
+                (split + 1, end),
+                right.clone(),
+            )),
+        },
+    }
+}
+
+/// Computes every value of every range of numbers bottom-up, remembering one way to get there.
+fn search_any_parenthesisation<'a, N: Number>(
+    equation: &Equation<N>,
+    operators: &[&'a dyn Operator<N>],
+) -> Option<Expression<'a, N>> {
+    let numbers = &equation.numbers;
+    let can_prune = can_prune(equation, operators);
+
+    // derivations[start][end] holds the values of all expressions over numbers[start..=end]
+    let mut derivations: Vec<Vec<Derivations<N>>> =
+        vec![vec![HashMap::new(); numbers.len()]; numbers.len()];
+
+    for (i, number) in numbers.iter().enumerate() {
+        derivations[i][i].insert(number.clone(), Derivation::Number);
+    }
+
+    for length in 2..=numbers.len() {
+        for start in 0..=numbers.len() - length {
+            let end = start + length - 1;
+            let mut values = HashMap::new();
+
+            for split in start..end {
+                for left in derivations[start][split].keys() {
+                    for right in derivations[split + 1][end].keys() {
+                        for &operator in operators {
+                            let Some(value) = operator.apply(left.clone(), right.clone()) else {
+                                continue;
+                            };
+
+                            if can_prune && value > equation.test_value {
+                                continue;
+                            }
+
+                            values
+                                .entry(value)
+                                .or_insert_with(|| Derivation::Operation {
+                                    operator,
+                                    split,
+                                    left: left.clone(),
+                                    right: right.clone(),
+                                });
+                        }
+                    }
+                }
+            }
+
+            derivations[start][end] = values;
+        }
+    }
+
+    derivations[0][numbers.len() - 1]
+        .contains_key(&equation.test_value)
+        .then(|| {
+            build_expression(
+                &derivations,
+                (0, numbers.len() - 1),
+                equation.test_value.clone(),
+            )
+        })
+}
+
+/// Finds an expression over the numbers of the equation that evaluates to its test value, using the
+/// given evaluation mode.
+pub(super) fn find_expression<'a, N: Number>(
+    equation: &'a Equation<N>,
+    operators: &[&'a dyn Operator<N>],
+    mode: EvaluationMode,
+) -> Option<Expression<'a, N>> {
+    assert!(!equation.numbers.is_empty(), "equation without numbers");
+
+    match mode {
+        EvaluationMode::LeftToRight => {
+            let solution = find_solution(equation, operators)?;
+
+            solution
+                .operators
+                .iter()
+                .zip(&equation.numbers[1..])
+                .fold(
+                    Expression::Number(equation.numbers[0].clone()),
+                    |left, (&operator, number)| Expression::Operation {
+                        operator,
+                        left: Box::new(left),
+                        right: Box::new(Expression::Number(number.clone())),
+                    },
+                )
+                .into()
+        }
+        EvaluationMode::Precedence => {
+            let mut chosen_operators = Vec::with_capacity(equation.numbers.len() - 1);
+
+            if !search_with_precedence(
+                equation,
+                operators,
+                can_prune(equation, operators),
+                (&equation.numbers[..1], &[]),
+                &mut chosen_operators,
+            ) {
+                return None;
+            }
+
+            combine_with_precedence(
+                equation
+                    .numbers
+                    .iter()
+                    .map(|number| Expression::Number(number.clone())),
+                &chosen_operators,
+                |operator, left, right| {
+                    Some(Expression::Operation {
+                        operator,
+                        left: Box::new(left),
+                        right: Box::new(right),
+                    })
+                },
+            )
+        }
+        EvaluationMode::AnyParenthesisation => search_any_parenthesisation(equation, operators),
+    }
+}
@@ -1,36 +1,154 @@
+use backwards::is_valid_equation_backwards;
+use constraints::OperatorConstraints;
+use evaluation::{find_expression, EvaluationMode};
+use number::Number;
+use operators::{parse_operator_set, OperatorSet, ADD_AND_MULTIPLY, ADD_MULTIPLY_AND_CONCATENATE};
 use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
-use std::{
-    iter,
-    sync::atomic::{AtomicU16, Ordering},
-    time::Instant,
-};
+use reachable::reachable_values;
+use solution::{count_solutions, find_all_constrained_solutions, find_constrained_solution};
+use std::time::Instant;
 
+use crate::progress::{progress_for, Progress};
+
+mod backwards;
+#[cfg(test)]
+mod benches;
+#[cfg(test)]
+mod combinatorics;
+mod constraints;
+mod evaluation;
+mod number;
+mod operators;
+#[cfg(test)]
 mod permutations;
+mod reachable;
+mod solution;
 #[cfg(test)]
 mod tests;
 
+/// The number type the puzzle is solved with. The left-to-right solvers work with any `Number`.
+#[cfg(not(feature = "wide-numbers"))]
 type NumberType = u64;
+#[cfg(feature = "wide-numbers")]
+type NumberType = u128;
 
 #[derive(Debug, PartialEq, PartialOrd, Clone)]
-struct Equation {
-    test_value: NumberType,
-    numbers: Vec<NumberType>,
-}
-
-#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Hash)]
-enum Operator {
-    ADD,
-    MULTIPLY,
-    CONCATENATE,
+struct Equation<N = NumberType> {
+    test_value: N,
+    numbers: Vec<N>,
 }
 
 pub(crate) fn seventh_december() {
     let equations = parse_equation_list(include_str!("part7-input.txt"));
 
-    dbg!(get_sum_of_valid_equations(equations));
+    let evaluation_mode = std::env::args()
+        .find_map(|argument| {
+            argument
+                .strip_prefix("--evaluation-mode=")
+                .map(|mode| mode.parse::<EvaluationMode>().unwrap())
+        })
+        .unwrap_or(EvaluationMode::LeftToRight);
+
+    let constraints = std::env::args()
+        .find_map(|argument| {
+            argument
+                .strip_prefix("--constraints=")
+                .map(|constraints| constraints.parse::<OperatorConstraints>().unwrap())
+        })
+        .unwrap_or_default();
+
+    if std::env::args().any(|argument| argument == "--print-solutions") {
+        for equation in &equations {
+            if evaluation_mode != EvaluationMode::LeftToRight {
+                match find_expression(equation, ADD_MULTIPLY_AND_CONCATENATE, evaluation_mode) {
+                    Some(expression) => println!("{expression} = {}", equation.test_value),
+                    None => println!("{}: no solution", equation.test_value),
+                }
+
+                continue;
+            }
+
+            let solutions = find_all_constrained_solutions(
+                equation,
+                ADD_MULTIPLY_AND_CONCATENATE,
+                &constraints,
+            );
+
+            println!("{}: {} solutions", equation.test_value, solutions.len());
+            for solution in solutions {
+                println!("  {solution}");
+            }
+        }
+    }
+
+    // `--operators=add,subtract` solves with those operators instead of the ones of both parts
+    let operator_sets = std::env::args()
+        .find_map(|argument| {
+            argument
+                .strip_prefix("--operators=")
+                .map(|names| vec![parse_operator_set(names).unwrap()])
+        })
+        .unwrap_or_else(|| {
+            vec![
+                ADD_AND_MULTIPLY.to_vec(),
+                ADD_MULTIPLY_AND_CONCATENATE.to_vec(),
+            ]
+        });
+
+    // `--reachable=6,8,6,15` lists every value the operators produce from those numbers
+    let reachable_from = std::env::args().find_map(|argument| {
+        argument.strip_prefix("--reachable=").map(|numbers| {
+            numbers
+                .split(',')
+                .map(|number| number.trim().parse::<NumberType>().unwrap())
+                .collect::<Vec<_>>()
+        })
+    });
+
+    for operators in &operator_sets {
+        let operator_names = operators
+            .iter()
+            .map(|operator| operator.name())
+            .collect::<Vec<_>>();
+
+        if let Some(numbers) = &reachable_from {
+            println!("{operator_names:?} from {numbers:?}:");
+            for (value, sequences) in reachable_values(numbers, operators, None) {
+                println!("  {value}: reached {sequences} times");
+            }
+        }
+
+        let progress = progress_for("seventh");
+
+        dbg!(
+            operator_names,
+            evaluation_mode,
+            get_sum_of_valid_equations(
+                equations.clone(),
+                operators,
+                evaluation_mode,
+                progress.as_ref()
+            )
+        );
+
+        // every operator sequence that solves an equation, not just whether there is one
+        let solutions = equations
+            .par_iter()
+            .map(|equation| count_solutions(equation, operators))
+            .sum::<usize>();
+        dbg!(solutions);
+
+        if constraints != OperatorConstraints::default() {
+            dbg!(
+                &constraints,
+                get_sum_of_constrained_equations(&equations, operators, &constraints)
+            );
+        }
+    }
 }
 
-fn parse_equation_list(input: impl AsRef<str>) -> Vec<Equation> {
+/// Parses one equation per line. Numbers may be negative if `N` allows it.
+fn parse_equation_list<N: Number>(input: impl AsRef<str>) -> Vec<Equation<N>> {
     let instant = Instant::now();
 
     let input = input.as_ref().trim();
@@ -42,14 +160,12 @@ fn parse_equation_list(input: impl AsRef<str>) -> Vec<Equation> {
         let test_value = components
             .next()
             .and_then(|x| x.strip_suffix(':'))
-            .and_then(|x| x.parse::<NumberType>().ok())
+            .and_then(|x| x.parse::<N>().ok())
             .unwrap();
 
         equations.push(Equation {
             test_value,
-            numbers: components
-                .map(|x| x.parse::<NumberType>().unwrap())
-                .collect(),
+            numbers: components.map(|x| x.parse::<N>().unwrap()).collect(),
         });
     }
 
@@ -58,82 +174,136 @@ fn parse_equation_list(input: impl AsRef<str>) -> Vec<Equation> {
     equations
 }
 
-fn apply_operators(equation: Equation, mut last_result: NumberType, operator: Operator) -> bool {
-    let Equation {
-        numbers,
-        test_value,
-    } = equation;
+/// Most numbers an equation can have for `apply_operators` to keep its search stack on the stack.
+/// Longer equations are searched with a stack on the heap.
+const MAX_NUMBERS_ON_STACK: usize = 32;
 
-    debug_assert!(!numbers.is_empty());
+/// Tries the operators from left to right with a depth-first search. The search keeps an explicit
+/// stack of intermediate results, so it doesn't allocate at all for primitive number types and
+/// equations of up to `MAX_NUMBERS_ON_STACK` numbers.
+fn apply_operators<N: Number>(
+    numbers: &[N],
+    test_value: N,
+    operators: &OperatorSet<N>,
+    can_prune: bool,
+) -> bool {
+    if numbers.len() <= MAX_NUMBERS_ON_STACK {
+        let mut results: [N; MAX_NUMBERS_ON_STACK] = std::array::from_fn(|_| N::zero());
+        let mut next_operators = [0; MAX_NUMBERS_ON_STACK];
 
-    let Some((next_number, numbers)) = numbers.split_first() else {
-        panic!();
+        search_operators(
+            numbers,
+            test_value,
+            operators,
+            can_prune,
+            &mut results,
+            &mut next_operators,
+        )
+    } else {
+        let mut results = vec![N::zero(); numbers.len()];
+        let mut next_operators = vec![0; numbers.len()];
+
+        search_operators(
+            numbers,
+            test_value,
+            operators,
+            can_prune,
+            &mut results,
+            &mut next_operators,
+        )
+    }
+}
+
+/// The search of `apply_operators`, with `results` and `next_operators` being at least as long as
+/// `numbers`.
+fn search_operators<N: Number>(
+    numbers: &[N],
+    test_value: N,
+    operators: &OperatorSet<N>,
+    can_prune: bool,
+    results: &mut [N],
+    next_operators: &mut [usize],
+) -> bool {
+    let Some(first_number) = numbers.first() else {
+        panic!("equation without numbers");
     };
 
-    match operator {
-        Operator::ADD => last_result += next_number,
-        Operator::MULTIPLY => last_result *= next_number,
-        Operator::CONCATENATE => {
-            last_result = format!("{last_result}{next_number}")
-                .parse::<NumberType>()
-                .unwrap()
+    // results[depth] is the result of the first `depth + 1` numbers, next_operators[depth] the
+    // operator to try next for combining it with the number after
+    let last_depth = numbers.len() - 1;
+    let mut depth = 0;
+
+    results[0] = first_number.clone();
+    next_operators[0] = 0;
+
+    loop {
+        if depth == last_depth && results[depth] == test_value {
+            return true;
         }
-    }
 
-    if last_result > test_value {
-        return false;
-    }
+        if depth == last_depth || next_operators[depth] == operators.len() {
+            // nothing left to try here, go back to the previous number
+            if depth == 0 {
+                return false;
+            }
 
-    if !numbers.is_empty() {
-        apply_operators(
-            Equation {
-                test_value,
-                numbers: numbers.to_vec(),
-            },
-            last_result,
-            Operator::ADD,
-        ) || apply_operators(
-            Equation {
-                test_value,
-                numbers: numbers.to_vec(),
-            },
-            last_result,
-            Operator::MULTIPLY,
-        ) || apply_operators(
-            Equation {
-                test_value,
-                numbers: numbers.to_vec(),
-            },
-            last_result,
-            Operator::CONCATENATE,
-        )
-    } else {
-        last_result == test_value
-    }
-}
+            depth -= 1;
+            continue;
+        }
 
-fn is_valid_equation(equation: Equation) -> bool {
-    let instant = Instant::now();
+        let operator = operators[next_operators[depth]];
+        next_operators[depth] += 1;
 
-    let result = apply_operators(equation.clone(), 0, Operator::ADD)
-        || apply_operators(equation.clone(), 0, Operator::MULTIPLY)
-        || apply_operators(equation.clone(), 0, Operator::CONCATENATE);
+        let Some(result) = operator.apply(results[depth].clone(), numbers[depth + 1].clone())
+        else {
+            continue;
+        };
 
-    dbg!(instant.elapsed());
+        if can_prune && result > test_value {
+            continue;
+        }
 
-    result
+        depth += 1;
+        results[depth] = result;
+        next_operators[depth] = 0;
+    }
 }
 
-fn get_sum_of_valid_equations(equation_list: Vec<Equation>) -> NumberType {
-    let processed_equations = AtomicU16::new(0);
+// Intermediate results can only grow when no operator ever decreases them, so we can give up as
+// soon as we exceed the test value. This only holds while all numbers are positive: a negative
+// number can bring an intermediate result back down, and multiplying by zero resets it.
+fn can_prune<N: Number>(equation: &Equation<N>, operators: &OperatorSet<N>) -> bool {
+    operators.iter().all(|operator| operator.never_decreases())
+        && equation.numbers.iter().all(|number| *number > N::zero())
+}
 
-    equation_list
+fn is_valid_equation<N: Number>(equation: &Equation<N>, operators: &OperatorSet<N>) -> bool {
+    apply_operators(
+        &equation.numbers,
+        equation.test_value.clone(),
+        operators,
+        can_prune(equation, operators),
+    )
+}
+
+fn get_sum_of_valid_equations(
+    equation_list: Vec<Equation>,
+    operators: &OperatorSet,
+    evaluation_mode: EvaluationMode,
+    progress: &dyn Progress,
+) -> NumberType {
+    progress.start(equation_list.len());
+
+    let sum = equation_list
         .into_par_iter()
         .map(|equation| {
             let test_value = equation.test_value;
 
-            let result = is_valid_equation(equation);
-            dbg!(processed_equations.fetch_add(1, Ordering::SeqCst));
+            let result = match evaluation_mode {
+                EvaluationMode::LeftToRight => is_valid_equation_backwards(&equation, operators),
+                mode => find_expression(&equation, operators, mode).is_some(),
+            };
+            progress.advance(1);
 
             if result {
                 test_value
@@ -141,5 +311,23 @@ fn get_sum_of_valid_equations(equation_list: Vec<Equation>) -> NumberType {
                 0
             }
         })
+        .sum();
+
+    progress.finish();
+
+    sum
+}
+
+/// Like `get_sum_of_valid_equations` when evaluating left-to-right, but only counts equations that
+/// have a solution satisfying the constraints.
+fn get_sum_of_constrained_equations(
+    equation_list: &[Equation],
+    operators: &OperatorSet,
+    constraints: &OperatorConstraints,
+) -> NumberType {
+    equation_list
+        .par_iter()
+        .filter(|equation| find_constrained_solution(equation, operators, constraints).is_some())
+        .map(|equation| equation.test_value)
         .sum()
 }
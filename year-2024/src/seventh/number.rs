@@ -0,0 +1,81 @@
+use std::{
+    fmt::{Debug, Display},
+    hash::Hash,
+    str::FromStr,
+};
+
+/// What the equation solvers need from a number type. Everything that can fail returns None, which
+/// the solvers treat as a dead branch. Only `Clone` is required, so that big integers that live on
+/// the heap can be used as well.
+pub(super) trait Number:
+    Clone + Ord + Hash + Debug + Display + FromStr<Err: Debug> + Send + Sync + 'static
+{
+    fn from_u8(value: u8) -> Self;
+
+    fn zero() -> Self {
+        Self::from_u8(0)
+    }
+
+    fn is_negative(&self) -> bool {
+        *self < Self::zero()
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+
+    fn checked_sub(&self, other: &Self) -> Option<Self>;
+
+    fn checked_mul(&self, other: &Self) -> Option<Self>;
+
+    /// Division rounding towards zero, None when dividing by zero.
+    fn checked_div(&self, other: &Self) -> Option<Self>;
+
+    /// Remainder of `checked_div`, with the sign of `self`.
+    fn checked_rem(&self, other: &Self) -> Option<Self>;
+
+    /// None for negative exponents as well.
+    fn checked_pow(&self, exponent: &Self) -> Option<Self>;
+
+    fn bit_xor(&self, other: &Self) -> Self;
+}
+
+macro_rules! impl_number_for_primitive {
+    ($($number_type:ty),*) => {
+        $(
+            impl Number for $number_type {
+                fn from_u8(value: u8) -> Self {
+                    value.into()
+                }
+
+                fn checked_add(&self, other: &Self) -> Option<Self> {
+                    <$number_type>::checked_add(*self, *other)
+                }
+
+                fn checked_sub(&self, other: &Self) -> Option<Self> {
+                    <$number_type>::checked_sub(*self, *other)
+                }
+
+                fn checked_mul(&self, other: &Self) -> Option<Self> {
+                    <$number_type>::checked_mul(*self, *other)
+                }
+
+                fn checked_div(&self, other: &Self) -> Option<Self> {
+                    <$number_type>::checked_div(*self, *other)
+                }
+
+                fn checked_rem(&self, other: &Self) -> Option<Self> {
+                    <$number_type>::checked_rem(*self, *other)
+                }
+
+                fn checked_pow(&self, exponent: &Self) -> Option<Self> {
+                    <$number_type>::checked_pow(*self, u32::try_from(*exponent).ok()?)
+                }
+
+                fn bit_xor(&self, other: &Self) -> Self {
+                    self ^ other
+                }
+            }
+        )*
+    };
+}
+
+impl_number_for_primitive!(u64, u128, i64, i128);
@@ -0,0 +1,321 @@
+use super::{number::Number, NumberType};
+
+/// An operator that can be placed between two numbers of an equation. When evaluating
+/// left-to-right, `left` is the result of everything before the operator.
+pub(super) trait Operator<N: Number = NumberType>: Sync {
+    fn name(&self) -> &'static str;
+
+    fn symbol(&self) -> &'static str;
+
+    /// Returns None when the result is not defined, e.g. when dividing by zero or when it doesn't
+    /// fit into `N`. Such branches are given up.
+    fn apply(&self, left: N, right: N) -> Option<N>;
+
+    /// Whether the result is never smaller than either operand as long as both are positive. Only
+    /// when all operators of a set guarantee this and all numbers are positive, we can stop as soon
+    /// as we exceed the test value. Negative numbers can bring the result back down.
+    fn never_decreases(&self) -> bool {
+        false
+    }
+
+    /// How tightly the operator binds when evaluating with conventional operator precedence, higher
+    /// binds tighter. Operators of the same precedence are evaluated left-to-right.
+    fn precedence(&self) -> u8 {
+        1
+    }
+
+    /// The operator as one that can be undone, which is what the backwards solver needs. Only
+    /// operators that implement `InvertibleOperator` can return themselves here.
+    fn as_invertible(&self) -> Option<&dyn InvertibleOperator<N>> {
+        None
+    }
+}
+
+/// An operator that can be undone, so that equations can be solved from right to left.
+pub(super) trait InvertibleOperator<N: Number = NumberType>: Operator<N> {
+    /// Returns the only `left` for which `apply(left, right)` is `result`, or None if there is
+    /// no such `left`.
+    fn unapply(&self, result: N, right: N) -> Option<N>;
+}
+
+pub(super) type OperatorSet<'a, N = NumberType> = [&'a dyn Operator<N>];
+
+/// Operators of part 1.
+pub(super) const ADD_AND_MULTIPLY: &OperatorSet = &[&Add, &Multiply];
+
+/// Operators of part 2.
+pub(super) const ADD_MULTIPLY_AND_CONCATENATE: &OperatorSet = &[&Add, &Multiply, &Concatenate];
+
+/// Every operator there is, to pick from by name.
+pub(super) const ALL_OPERATORS: &OperatorSet = &[
+    &Add,
+    &Multiply,
+    &Concatenate,
+    &Subtract,
+    &Divide,
+    &Exponentiate,
+    &Xor,
+];
+
+/// Picks operators from `ALL_OPERATORS` by their comma separated names, e.g. `add,subtract`.
+pub(super) fn parse_operator_set(names: &str) -> Result<Vec<&'static dyn Operator>, String> {
+    names
+        .split(',')
+        .map(str::trim)
+        .map(|name| {
+            ALL_OPERATORS
+                .iter()
+                .copied()
+                .find(|operator| operator.name() == name)
+                .ok_or_else(|| format!("unknown operator {name:?}"))
+        })
+        .collect()
+}
+
+pub(super) struct Add;
+
+impl<N: Number> Operator<N> for Add {
+    fn name(&self) -> &'static str {
+        "add"
+    }
+
+    fn symbol(&self) -> &'static str {
+        "+"
+    }
+
+    fn apply(&self, left: N, right: N) -> Option<N> {
+        left.checked_add(&right)
+    }
+
+    fn never_decreases(&self) -> bool {
+        true
+    }
+
+    fn as_invertible(&self) -> Option<&dyn InvertibleOperator<N>> {
+        Some(self)
+    }
+}
+
+impl<N: Number> InvertibleOperator<N> for Add {
+    fn unapply(&self, result: N, right: N) -> Option<N> {
+        result.checked_sub(&right)
+    }
+}
+
+pub(super) struct Multiply;
+
+impl<N: Number> Operator<N> for Multiply {
+    fn name(&self) -> &'static str {
+        "multiply"
+    }
+
+    fn symbol(&self) -> &'static str {
+        "*"
+    }
+
+    fn apply(&self, left: N, right: N) -> Option<N> {
+        left.checked_mul(&right)
+    }
+
+    fn never_decreases(&self) -> bool {
+        true
+    }
+
+    fn precedence(&self) -> u8 {
+        2
+    }
+
+    fn as_invertible(&self) -> Option<&dyn InvertibleOperator<N>> {
+        Some(self)
+    }
+}
+
+impl<N: Number> InvertibleOperator<N> for Multiply {
+    fn unapply(&self, result: N, right: N) -> Option<N> {
+        // any `left` works when multiplying by zero, so there is no unique one
+        if right == N::zero() || result.checked_rem(&right)? != N::zero() {
+            return None;
+        }
+
+        result.checked_div(&right)
+    }
+}
+
+pub(super) struct Concatenate;
+
+/// Returns the smallest power of ten that is greater than `number`, which is what a number has to be
+/// multiplied with to make room for the decimal digits of `number`. Returns None on overflow.
+fn power_of_ten_above<N: Number>(number: &N) -> Option<N> {
+    let ten = N::from_u8(10);
+    let mut power_of_ten = ten.clone();
+
+    while power_of_ten <= *number {
+        power_of_ten = power_of_ten.checked_mul(&ten)?;
+    }
+
+    Some(power_of_ten)
+}
+
+impl<N: Number> Operator<N> for Concatenate {
+    fn name(&self) -> &'static str {
+        "concatenate"
+    }
+
+    fn symbol(&self) -> &'static str {
+        "||"
+    }
+
+    fn apply(&self, left: N, right: N) -> Option<N> {
+        // the digits of a negative number can't be appended, a negative `left` stays negative,
+        // -12 || 3 is -123
+        if right.is_negative() {
+            return None;
+        }
+
+        let shifted = left.checked_mul(&power_of_ten_above(&right)?)?;
+
+        if left.is_negative() {
+            shifted.checked_sub(&right)
+        } else {
+            shifted.checked_add(&right)
+        }
+    }
+
+    fn never_decreases(&self) -> bool {
+        true
+    }
+
+    fn precedence(&self) -> u8 {
+        4
+    }
+
+    fn as_invertible(&self) -> Option<&dyn InvertibleOperator<N>> {
+        Some(self)
+    }
+}
+
+impl<N: Number> InvertibleOperator<N> for Concatenate {
+    fn unapply(&self, result: N, right: N) -> Option<N> {
+        // strip the decimal digits of `right` from the end of `result`
+        if right.is_negative() {
+            return None;
+        }
+
+        let power_of_ten = power_of_ten_above(&right)?;
+
+        if result.is_negative() {
+            // undo -123 = -12 * 10 - 3, where `left` has to be negative as well
+            let shifted = result.checked_add(&right)?;
+            let left = shifted.checked_div(&power_of_ten)?;
+
+            if shifted.checked_rem(&power_of_ten)? != N::zero() || !left.is_negative() {
+                return None;
+            }
+
+            return Some(left);
+        }
+
+        if result.checked_rem(&power_of_ten)? != right {
+            return None;
+        }
+
+        result.checked_div(&power_of_ten)
+    }
+}
+
+pub(super) struct Subtract;
+
+impl<N: Number> Operator<N> for Subtract {
+    fn name(&self) -> &'static str {
+        "subtract"
+    }
+
+    fn symbol(&self) -> &'static str {
+        "-"
+    }
+
+    fn apply(&self, left: N, right: N) -> Option<N> {
+        left.checked_sub(&right)
+    }
+
+    fn as_invertible(&self) -> Option<&dyn InvertibleOperator<N>> {
+        Some(self)
+    }
+}
+
+impl<N: Number> InvertibleOperator<N> for Subtract {
+    fn unapply(&self, result: N, right: N) -> Option<N> {
+        result.checked_add(&right)
+    }
+}
+
+/// Integer division, rounding towards zero.
+pub(super) struct Divide;
+
+impl<N: Number> Operator<N> for Divide {
+    fn name(&self) -> &'static str {
+        "divide"
+    }
+
+    fn symbol(&self) -> &'static str {
+        "/"
+    }
+
+    fn apply(&self, left: N, right: N) -> Option<N> {
+        left.checked_div(&right)
+    }
+
+    fn precedence(&self) -> u8 {
+        2
+    }
+}
+
+pub(super) struct Exponentiate;
+
+impl<N: Number> Operator<N> for Exponentiate {
+    fn name(&self) -> &'static str {
+        "exponentiate"
+    }
+
+    fn symbol(&self) -> &'static str {
+        "^"
+    }
+
+    fn apply(&self, left: N, right: N) -> Option<N> {
+        left.checked_pow(&right)
+    }
+
+    fn precedence(&self) -> u8 {
+        3
+    }
+}
+
+pub(super) struct Xor;
+
+impl<N: Number> Operator<N> for Xor {
+    fn name(&self) -> &'static str {
+        "xor"
+    }
+
+    fn symbol(&self) -> &'static str {
+        "xor"
+    }
+
+    fn apply(&self, left: N, right: N) -> Option<N> {
+        Some(left.bit_xor(&right))
+    }
+
+    fn precedence(&self) -> u8 {
+        0
+    }
+
+    fn as_invertible(&self) -> Option<&dyn InvertibleOperator<N>> {
+        Some(self)
+    }
+}
+
+impl<N: Number> InvertibleOperator<N> for Xor {
+    fn unapply(&self, result: N, right: N) -> Option<N> {
+        Some(result.bit_xor(&right))
+    }
+}
@@ -1,87 +1,192 @@
-use std::{
-    collections::{btree_set::IntoIter, BTreeSet},
-    time::Instant,
-};
-
-pub(super) enum UniquePermutations<T> {
-    Leaf {
-        elements: Option<Vec<T>>,
-    },
-    Stem {
-        elements: Vec<T>,
-        unique_elements: IntoIter<T>,
-        first_element: T,
-        inner: Box<Self>,
-    },
+/// Iterates over all distinct orderings of a multiset in lexicographic order. The elements are kept
+/// in a single buffer that is rearranged in place from one permutation to the next.
+pub(super) struct UniquePermutations<T> {
+    elements: Vec<T>,
+    /// Number of permutations that haven't been yielded yet, including the one in `elements`. None
+    /// if there are too many to count.
+    remaining: Option<usize>,
+    started: bool,
+    finished: bool,
 }
 
-impl<T: Copy + Clone + Ord> UniquePermutations<T> {
-    pub(super) fn new(elements: Vec<T>) -> Self {
-        if elements.len() == 1 {
-            let elements = Some(elements);
-            Self::Leaf { elements }
-        } else {
-            let mut unique_elements = elements
-                .clone()
-                .into_iter()
-                .collect::<BTreeSet<_>>()
-                .into_iter();
-
-            let (first_element, inner) = Self::next_level(&mut unique_elements, elements.clone())
-                .expect("Must have at least one item");
-
-            Self::Stem {
-                elements,
-                unique_elements,
-                first_element,
-                inner,
-            }
+impl<T: Ord> UniquePermutations<T> {
+    pub(super) fn new(mut elements: Vec<T>) -> Self {
+        elements.sort_unstable();
+        let remaining = count_unique_permutations(&elements);
+
+        Self {
+            elements,
+            remaining,
+            started: false,
+            finished: false,
         }
     }
 
-    fn next_level(
-        mut unique_elements: impl Iterator<Item = T>,
-        elements: Vec<T>,
-    ) -> Option<(T, Box<Self>)> {
-        let first_element = unique_elements.next()?;
-
-        let mut remaining_elements = elements;
+    /// Lending version of `next`: rearranges the buffer into the next permutation and hands it
+    /// out, without allocating.
+    pub(super) fn next_slice(&mut self) -> Option<&[T]> {
+        if self.finished {
+            return None;
+        }
 
-        if let Some(idx) = remaining_elements.iter().position(|&i| i == first_element) {
-            remaining_elements.remove(idx);
+        if self.started && !next_permutation(&mut self.elements) {
+            self.finished = true;
+            return None;
         }
 
-        let inner = Box::new(Self::new(remaining_elements));
+        self.started = true;
+        self.remaining = self.remaining.map(|remaining| remaining - 1);
 
-        Some((first_element, inner))
+        Some(&self.elements)
     }
 }
 
-impl<T: Copy + Clone + Ord> Iterator for UniquePermutations<T> {
+impl<T: Ord + Clone> Iterator for UniquePermutations<T> {
     type Item = Vec<T>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self {
-            Self::Leaf { elements } => elements.take(),
-            Self::Stem {
-                elements,
-                unique_elements,
-                first_element,
-                inner,
-            } => loop {
-                match inner.next() {
-                    Some(mut v) => {
-                        v.insert(0, *first_element);
-                        return Some(v);
-                    }
-                    None => {
-                        let (next_fe, next_i) =
-                            Self::next_level(&mut *unique_elements, elements.clone())?;
-                        *first_element = next_fe;
-                        *inner = next_i;
-                    }
+        self.next_slice().map(<[T]>::to_vec)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining.unwrap_or(usize::MAX), self.remaining)
+    }
+}
+
+/// Rearranges `elements` into the lexicographically next greater permutation. Returns false, and
+/// leaves `elements` untouched, if it already is the greatest one.
+pub(super) fn next_permutation<T: Ord>(elements: &mut [T]) -> bool {
+    // the longest non-increasing suffix can't be made any greater on its own
+    let Some(pivot) = elements.windows(2).rposition(|pair| pair[0] < pair[1]) else {
+        return false;
+    };
+
+    // swap the pivot with the smallest element of the suffix that is still greater than it, then
+    // put the suffix in ascending order to get the smallest continuation
+    let successor = elements
+        .iter()
+        .rposition(|element| *element > elements[pivot])
+        .expect("the element after the pivot is greater");
+    elements.swap(pivot, successor);
+    elements[pivot + 1..].reverse();
+
+    true
+}
+
+/// Multinomial coefficient n! / (k_1! * ... * k_m!) of the sorted `elements`, where the k_i are the
+/// lengths of runs of equal elements. Computed as a product of binomial coefficients so that the
+/// intermediate values stay small. None if the count doesn't fit into `usize`.
+fn count_unique_permutations<T: Ord>(elements: &[T]) -> Option<usize> {
+    let mut count: usize = 1;
+    let mut placed = 0;
+
+    for group in elements.chunk_by(|a, b| a == b) {
+        // choose the places of this group among all places used so far
+        for i in 1..=group.len() {
+            placed += 1;
+            // count * placed / i is always a whole number, divide first where possible
+            let divisor = gcd(count, i);
+            count = (count / divisor).checked_mul(placed / (i / divisor))?;
+        }
+    }
+
+    Some(count)
+}
+
+fn gcd(mut a: usize, mut b: usize) -> usize {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+
+    a
+}
+
+/// The original implementation, which builds a tree of iterators, one per remaining element. Only
+/// kept around to compare the results and timings.
+pub(super) mod recursive {
+    use std::collections::{btree_set::IntoIter, BTreeSet};
+
+    pub(in super::super) enum RecursiveUniquePermutations<T> {
+        Leaf {
+            elements: Option<Vec<T>>,
+        },
+        Stem {
+            elements: Vec<T>,
+            unique_elements: IntoIter<T>,
+            first_element: T,
+            inner: Box<Self>,
+        },
+    }
+
+    impl<T: Copy + Clone + Ord> RecursiveUniquePermutations<T> {
+        pub(in super::super) fn new(elements: Vec<T>) -> Self {
+            if elements.len() == 1 {
+                let elements = Some(elements);
+                Self::Leaf { elements }
+            } else {
+                let mut unique_elements = elements
+                    .clone()
+                    .into_iter()
+                    .collect::<BTreeSet<_>>()
+                    .into_iter();
+
+                let (first_element, inner) =
+                    Self::next_level(&mut unique_elements, elements.clone())
+                        .expect("Must have at least one item");
+
+                Self::Stem {
+                    elements,
+                    unique_elements,
+                    first_element,
+                    inner,
                 }
-            },
+            }
+        }
+
+        fn next_level(
+            mut unique_elements: impl Iterator<Item = T>,
+            elements: Vec<T>,
+        ) -> Option<(T, Box<Self>)> {
+            let first_element = unique_elements.next()?;
+
+            let mut remaining_elements = elements;
+
+            if let Some(idx) = remaining_elements.iter().position(|&i| i == first_element) {
+                remaining_elements.remove(idx);
+            }
+
+            let inner = Box::new(Self::new(remaining_elements));
+
+            Some((first_element, inner))
+        }
+    }
+
+    impl<T: Copy + Clone + Ord> Iterator for RecursiveUniquePermutations<T> {
+        type Item = Vec<T>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            match self {
+                Self::Leaf { elements } => elements.take(),
+                Self::Stem {
+                    elements,
+                    unique_elements,
+                    first_element,
+                    inner,
+                } => loop {
+                    match inner.next() {
+                        Some(mut v) => {
+                            v.insert(0, *first_element);
+                            return Some(v);
+                        }
+                        None => {
+                            let (next_fe, next_i) =
+                                Self::next_level(&mut *unique_elements, elements.clone())?;
+                            *first_element = next_fe;
+                            *inner = next_i;
+                        }
+                    }
+                },
+            }
         }
     }
 }
@@ -0,0 +1,58 @@
+use std::collections::{BTreeMap, HashMap};
+
+use super::{number::Number, operators::OperatorSet};
+
+/// Returns every value the operators can produce from the numbers (evaluated left-to-right),
+/// together with how many operator sequences produce it. Values above `upper_bound` are left out.
+///
+/// All sequences that lead to the same intermediate value continue identically, so we only keep
+/// each intermediate value once with its count, instead of following every sequence.
+///
+/// # Panics
+/// When a count doesn't fit into `u128`, which takes more than 81 numbers with three operators.
+pub(super) fn reachable_values<N: Number>(
+    numbers: &[N],
+    operators: &OperatorSet<N>,
+    upper_bound: Option<N>,
+) -> BTreeMap<N, u128> {
+    let Some((first_number, numbers)) = numbers.split_first() else {
+        panic!("can't reach anything without numbers");
+    };
+
+    // values above the bound can only come back down with operators that decrease values
+    let can_prune = operators.iter().all(|operator| operator.never_decreases())
+        && *first_number > N::zero()
+        && numbers.iter().all(|number| *number > N::zero());
+    let is_within_bound = |value: &N| upper_bound.as_ref().is_none_or(|bound| value <= bound);
+
+    let mut reachable: HashMap<N, u128> = HashMap::from([(first_number.clone(), 1)]);
+
+    for number in numbers {
+        let mut next_reachable: HashMap<N, u128> =
+            HashMap::with_capacity(reachable.len() * operators.len());
+
+        for (value, &count) in &reachable {
+            for operator in operators {
+                let Some(next_value) = operator.apply(value.clone(), number.clone()) else {
+                    continue;
+                };
+
+                if can_prune && !is_within_bound(&next_value) {
+                    continue;
+                }
+
+                let next_count = next_reachable.entry(next_value).or_default();
+                *next_count = next_count
+                    .checked_add(count)
+                    .expect("Too many operator sequences to count");
+            }
+        }
+
+        reachable = next_reachable;
+    }
+
+    reachable
+        .into_iter()
+        .filter(|(value, _)| is_within_bound(value))
+        .collect()
+}
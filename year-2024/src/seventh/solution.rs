@@ -0,0 +1,188 @@
+use std::{fmt, ops::ControlFlow};
+
+use super::{
+    can_prune, constraints::OperatorConstraints, number::Number, operators::Operator, Equation,
+    NumberType,
+};
+
+/// Operators which, placed between the numbers of an equation, produce its test value.
+pub(super) struct Solution<'a, N: Number = NumberType> {
+    pub(super) equation: &'a Equation<N>,
+    pub(super) operators: Vec<&'a dyn Operator<N>>,
+}
+
+impl<N: Number> fmt::Display for Solution<'_, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some((first_number, numbers)) = self.equation.numbers.split_first() else {
+            return write!(f, "= {}", self.equation.test_value);
+        };
+
+        write!(f, "{first_number}")?;
+
+        for (operator, number) in self.operators.iter().zip(numbers) {
+            write!(f, " {} {number}", operator.symbol())?;
+        }
+
+        write!(f, " = {}", self.equation.test_value)
+    }
+}
+
+impl<N: Number> fmt::Debug for Solution<'_, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+/// Tries all operator sequences that satisfy the constraints depth-first and hands every one that
+/// produces the test value to `on_solution`, until it asks to stop. Sequences that can't satisfy the
+/// constraints anymore are given up as soon as possible.
+#[allow(clippy::too_many_arguments)]
+fn search_solutions<'a, N: Number>(
+    numbers: &[N],
+    test_value: &N,
+    last_result: N,
+    operators: &[&'a dyn Operator<N>],
+    can_prune: bool,
+    constraints: &OperatorConstraints,
+    gap_count: usize,
+    chosen_operators: &mut Vec<&'a dyn Operator<N>>,
+    on_solution: &mut impl FnMut(&[&'a dyn Operator<N>]) -> ControlFlow<()>,
+) -> ControlFlow<()> {
+    let Some((next_number, numbers)) = numbers.split_first() else {
+        if last_result == *test_value {
+            return on_solution(chosen_operators);
+        }
+
+        return ControlFlow::Continue(());
+    };
+
+    for &operator in operators {
+        let Some(result) = operator.apply(last_result.clone(), next_number.clone()) else {
+            continue;
+        };
+
+        if can_prune && result > *test_value {
+            continue;
+        }
+
+        chosen_operators.push(operator);
+        let control_flow = if constraints.allows(chosen_operators, gap_count) {
+            search_solutions(
+                numbers,
+                test_value,
+                result,
+                operators,
+                can_prune,
+                constraints,
+                gap_count,
+                chosen_operators,
+                on_solution,
+            )
+        } else {
+            ControlFlow::Continue(())
+        };
+        chosen_operators.pop();
+
+        control_flow?;
+    }
+
+    ControlFlow::Continue(())
+}
+
+fn for_each_solution<'a, N: Number>(
+    equation: &Equation<N>,
+    operators: &[&'a dyn Operator<N>],
+    constraints: &OperatorConstraints,
+    mut on_solution: impl FnMut(&[&'a dyn Operator<N>]) -> ControlFlow<()>,
+) {
+    let Some((first_number, numbers)) = equation.numbers.split_first() else {
+        panic!("equation without numbers");
+    };
+
+    if !constraints.allows::<N>(&[], numbers.len()) {
+        return;
+    }
+
+    let _ = search_solutions(
+        numbers,
+        &equation.test_value,
+        first_number.clone(),
+        operators,
+        can_prune(equation, operators),
+        constraints,
+        numbers.len(),
+        &mut Vec::with_capacity(numbers.len()),
+        &mut on_solution,
+    );
+}
+
+/// Returns the first operator sequence (in the order of the operator set) that makes the equation
+/// valid, or None when there is none.
+pub(super) fn find_solution<'a, N: Number>(
+    equation: &'a Equation<N>,
+    operators: &[&'a dyn Operator<N>],
+) -> Option<Solution<'a, N>> {
+    find_constrained_solution(equation, operators, &OperatorConstraints::default())
+}
+
+/// Like `find_solution`, but only considers operator sequences that satisfy the constraints.
+pub(super) fn find_constrained_solution<'a, N: Number>(
+    equation: &'a Equation<N>,
+    operators: &[&'a dyn Operator<N>],
+    constraints: &OperatorConstraints,
+) -> Option<Solution<'a, N>> {
+    let mut solution = None;
+
+    for_each_solution(equation, operators, constraints, |chosen_operators| {
+        solution = Some(Solution {
+            equation,
+            operators: chosen_operators.to_vec(),
+        });
+
+        ControlFlow::Break(())
+    });
+
+    solution
+}
+
+#[cfg(test)]
+pub(super) fn find_all_solutions<'a, N: Number>(
+    equation: &'a Equation<N>,
+    operators: &[&'a dyn Operator<N>],
+) -> Vec<Solution<'a, N>> {
+    find_all_constrained_solutions(equation, operators, &OperatorConstraints::default())
+}
+
+pub(super) fn find_all_constrained_solutions<'a, N: Number>(
+    equation: &'a Equation<N>,
+    operators: &[&'a dyn Operator<N>],
+    constraints: &OperatorConstraints,
+) -> Vec<Solution<'a, N>> {
+    let mut solutions = Vec::new();
+
+    for_each_solution(equation, operators, constraints, |chosen_operators| {
+        solutions.push(Solution {
+            equation,
+            operators: chosen_operators.to_vec(),
+        });
+
+        ControlFlow::Continue(())
+    });
+
+    solutions
+}
+
+pub(super) fn count_solutions<N: Number>(
+    equation: &Equation<N>,
+    operators: &[&dyn Operator<N>],
+) -> usize {
+    let mut count = 0;
+
+    for_each_solution(equation, operators, &OperatorConstraints::default(), |_| {
+        count += 1;
+
+        ControlFlow::Continue(())
+    });
+
+    count
+}
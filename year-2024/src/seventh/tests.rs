@@ -1,6 +1,32 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    fmt,
+    str::FromStr,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use crate::progress::{Progress, SilentProgress};
+use crate::pseudo_random::PseudoRandom;
 use crate::seventh::{
-    apply_operators, get_all_permutations, get_sum_of_valid_equations, is_valid_equation,
-    parse_equation_list, Equation, Operator,
+    apply_operators,
+    backwards::is_valid_equation_backwards,
+    combinatorics::{CartesianPower, Combinations, KPermutations, SequenceOrder},
+    constraints::OperatorConstraints,
+    evaluation::{find_expression, EvaluationMode},
+    get_sum_of_valid_equations, is_valid_equation,
+    number::Number,
+    operators::{
+        parse_operator_set, Add, Concatenate, Divide, Exponentiate, InvertibleOperator, Multiply,
+        Operator, OperatorSet, Subtract, Xor, ADD_AND_MULTIPLY, ADD_MULTIPLY_AND_CONCATENATE,
+    },
+    parse_equation_list,
+    permutations::{recursive::RecursiveUniquePermutations, UniquePermutations},
+    reachable::reachable_values,
+    solution::{
+        count_solutions, find_all_constrained_solutions, find_all_solutions, find_solution,
+    },
+    Equation, NumberType,
 };
 
 #[test]
@@ -16,7 +42,7 @@ fn test_sample() {
 21037: 9 7 18 13
 292: 11 6 16 20";
 
-    let equations = parse_equation_list(SAMPLE);
+    let equations = parse_equation_list::<NumberType>(SAMPLE);
 
     assert_eq!(
         equations,
@@ -63,34 +89,32 @@ fn test_sample() {
 
 #[test]
 fn test_permutations() {
-    let permutations = get_all_permutations(1).collect::<Vec<_>>();
+    let permutations =
+        CartesianPower::new(ADD_AND_MULTIPLY.to_vec(), 2, SequenceOrder::Lexicographic)
+            .map(|operators| operators.iter().map(|operator| operator.name()).collect())
+            .collect::<Vec<Vec<_>>>();
 
     dbg!(&permutations);
 
-    use Operator::*;
     assert_eq!(
         permutations,
         [
-            [ADD, ADD],
-            [ADD, MULTIPLY],
-            [MULTIPLY, ADD],
-            [MULTIPLY, MULTIPLY],
+            ["add", "add"],
+            ["add", "multiply"],
+            ["multiply", "add"],
+            ["multiply", "multiply"],
         ]
     );
 }
 
 #[test]
 fn test_apply_operators() {
-    assert_eq!(
-        apply_operators(
-            &Equation {
-                test_value: 190,
-                numbers: vec![10, 19]
-            },
-            &vec![Operator::MULTIPLY]
-        ),
+    assert!(apply_operators::<NumberType>(
+        &[10, 19],
+        190,
+        &[&Multiply],
         true
-    );
+    ));
 }
 
 #[test]
@@ -100,11 +124,34 @@ fn test_is_valid_equation() {
         numbers: vec![10, 19],
     };
 
-    assert!(is_valid_equation(&input));
+    assert!(is_valid_equation(&input, ADD_AND_MULTIPLY));
+    assert!(!is_valid_equation(&input, &[&Add]));
+}
+
+#[derive(Default)]
+struct RecordingProgress {
+    total: AtomicUsize,
+    done: AtomicUsize,
+    finished: AtomicBool,
+}
+
+impl Progress for RecordingProgress {
+    fn start(&self, total: usize) {
+        self.total.store(total, Ordering::Relaxed);
+    }
+
+    fn advance(&self, count: usize) {
+        self.done.fetch_add(count, Ordering::Relaxed);
+    }
+
+    fn finish(&self) {
+        self.finished.store(true, Ordering::Relaxed);
+    }
 }
 
 #[test]
 fn test_sum_of_valid_equations() {
+    let progress = RecordingProgress::default();
     let equation_list = vec![
         Equation {
             test_value: 190,
@@ -144,5 +191,1064 @@ fn test_sum_of_valid_equations() {
         },
     ];
 
-    assert_eq!(11387, get_sum_of_valid_equations(equation_list));
+    assert_eq!(
+        3749,
+        get_sum_of_valid_equations(
+            equation_list.clone(),
+            ADD_AND_MULTIPLY,
+            EvaluationMode::LeftToRight,
+            &SilentProgress
+        )
+    );
+    assert_eq!(
+        11387,
+        get_sum_of_valid_equations(
+            equation_list,
+            ADD_MULTIPLY_AND_CONCATENATE,
+            EvaluationMode::LeftToRight,
+            &progress
+        )
+    );
+    assert_eq!(progress.total.load(Ordering::Relaxed), 9);
+    assert_eq!(progress.done.load(Ordering::Relaxed), 9);
+    assert!(progress.finished.load(Ordering::Relaxed));
+}
+
+#[test]
+fn test_custom_operator_sets() {
+    const WITH_SUBTRACTION_AND_DIVISION: &OperatorSet = &[&Add, &Subtract, &Divide];
+
+    // 20 - 5 / 3 = 5
+    let equation_with_subtraction = Equation {
+        test_value: 5,
+        numbers: vec![20, 5, 3],
+    };
+    assert!(is_valid_equation(
+        &equation_with_subtraction,
+        WITH_SUBTRACTION_AND_DIVISION
+    ));
+    assert!(!is_valid_equation(
+        &equation_with_subtraction,
+        ADD_MULTIPLY_AND_CONCATENATE
+    ));
+
+    // 2 ^ 3 xor 1 = 9
+    let equation: Equation = Equation {
+        test_value: 9,
+        numbers: vec![2, 3, 1],
+    };
+    assert!(is_valid_equation(&equation, &[&Exponentiate, &Xor]));
+
+    // operators which would produce an undefined result are skipped
+    assert_eq!(<Subtract as Operator>::apply(&Subtract, 3, 5), None);
+    assert_eq!(<Divide as Operator>::apply(&Divide, 3, 0), None);
+    assert_eq!(<Exponentiate as Operator>::symbol(&Exponentiate), "^");
+    assert_eq!(<Xor as Operator>::name(&Xor), "xor");
+
+    let operators = parse_operator_set("add, subtract,divide").unwrap();
+    assert!(is_valid_equation(&equation_with_subtraction, &operators));
+    assert_eq!(
+        operators
+            .iter()
+            .map(|operator| operator.symbol())
+            .collect::<Vec<_>>(),
+        ["+", "-", "/"]
+    );
+    assert_eq!(
+        parse_operator_set("add,modulo").map(|_| ()),
+        Err("unknown operator \"modulo\"".to_string())
+    );
+}
+
+#[test]
+fn test_solutions() {
+    let equation = Equation {
+        test_value: 3267,
+        numbers: vec![81, 40, 27],
+    };
+
+    let solution = find_solution(&equation, ADD_AND_MULTIPLY).unwrap();
+    assert_eq!(solution.to_string(), "81 + 40 * 27 = 3267");
+
+    let solutions = find_all_solutions(&equation, ADD_AND_MULTIPLY)
+        .iter()
+        .map(|solution| solution.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(solutions, ["81 + 40 * 27 = 3267", "81 * 40 + 27 = 3267"]);
+    assert_eq!(count_solutions(&equation, ADD_AND_MULTIPLY), 2);
+
+    let equation = Equation {
+        test_value: 7290,
+        numbers: vec![6, 8, 6, 15],
+    };
+    assert!(find_solution(&equation, ADD_AND_MULTIPLY).is_none());
+    assert_eq!(
+        find_solution(&equation, ADD_MULTIPLY_AND_CONCATENATE)
+            .unwrap()
+            .to_string(),
+        "6 * 8 || 6 * 15 = 7290"
+    );
+}
+
+#[test]
+fn test_backwards_solver_matches_forwards_solver() {
+    const SAMPLE: &str = "
+190: 10 19
+3267: 81 40 27
+83: 17 5
+156: 15 6
+7290: 6 8 6 15
+161011: 16 10 13
+192: 17 8 14
+21037: 9 7 18 13
+292: 11 6 16 20";
+
+    for input in [SAMPLE, include_str!("part7-input.txt")] {
+        for operators in [ADD_AND_MULTIPLY, ADD_MULTIPLY_AND_CONCATENATE] {
+            for equation in parse_equation_list(input) {
+                assert_eq!(
+                    is_valid_equation_backwards(&equation, operators),
+                    is_valid_equation(&equation, operators),
+                    "{equation:?}"
+                );
+            }
+        }
+    }
+
+    // falls back to searching forwards for operators that can't be undone
+    let equation: Equation = Equation {
+        test_value: 5,
+        numbers: vec![20, 5, 3],
+    };
+    assert!(is_valid_equation_backwards(
+        &equation,
+        &[&Add, &Subtract, &Divide]
+    ));
+}
+
+thread_local! {
+    static ALLOCATIONS_ON_THREAD: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Counts the allocations of each thread, so tests running in parallel don't interfere.
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ =
+            ALLOCATIONS_ON_THREAD.try_with(|allocations| allocations.set(allocations.get() + 1));
+
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn count_allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let allocations_before = ALLOCATIONS_ON_THREAD.with(Cell::get);
+    let result = f();
+    let allocations_after = ALLOCATIONS_ON_THREAD.with(Cell::get);
+
+    (result, allocations_after - allocations_before)
+}
+
+#[test]
+fn test_is_valid_equation_does_not_allocate() {
+    // make sure the allocations are counted at all
+    assert_eq!(count_allocations(|| Vec::<u8>::with_capacity(1)).1, 1);
+
+    let equations = parse_equation_list(include_str!("part7-input.txt"));
+    let mut valid_equations = 0;
+
+    for equation in &equations {
+        for operators in [ADD_AND_MULTIPLY, ADD_MULTIPLY_AND_CONCATENATE] {
+            let (is_valid, allocations) =
+                count_allocations(|| is_valid_equation(equation, operators));
+
+            assert_eq!(allocations, 0, "{equation:?}");
+
+            if is_valid {
+                valid_equations += 1;
+            }
+        }
+    }
+
+    assert!(valid_equations > 0);
+}
+
+#[test]
+fn test_overflow_is_a_dead_branch() {
+    // 2^32 * 2^32 wraps around to 0 in u64, which used to make this equation valid
+    let equation = Equation {
+        test_value: 5,
+        numbers: vec![1 << 32, 1 << 32, 5],
+    };
+
+    assert!(!is_valid_equation(&equation, ADD_AND_MULTIPLY));
+    assert!(!is_valid_equation_backwards(&equation, ADD_AND_MULTIPLY));
+
+    let largest_number = NumberType::MAX;
+    assert_eq!(Add.apply(largest_number, 1), None);
+    assert_eq!(Multiply.apply(largest_number, 2), None);
+    assert_eq!(Concatenate.apply(largest_number, 1), None);
+    assert_eq!(
+        <Concatenate as Operator>::apply(&Concatenate, 12, 345),
+        Some(12345)
+    );
+    assert_eq!(
+        <Concatenate as Operator>::apply(&Concatenate, 12, 0),
+        Some(120)
+    );
+    assert_eq!(
+        <Concatenate as InvertibleOperator>::unapply(&Concatenate, 12345, 345),
+        Some(12)
+    );
+    assert_eq!(
+        <Concatenate as InvertibleOperator>::unapply(&Concatenate, 12345, 45),
+        Some(123)
+    );
+    assert_eq!(
+        <Concatenate as InvertibleOperator>::unapply(&Concatenate, 12345, 5),
+        Some(1234)
+    );
+    assert_eq!(
+        <Concatenate as InvertibleOperator>::unapply(&Concatenate, 12345, 4),
+        None
+    );
+}
+
+#[cfg(feature = "wide-numbers")]
+#[test]
+fn test_wide_numbers() {
+    let equation = Equation {
+        test_value: (1 << 80) + 7,
+        numbers: vec![1 << 40, 1 << 40, 7],
+    };
+
+    assert!(is_valid_equation(&equation, ADD_AND_MULTIPLY));
+    assert!(is_valid_equation_backwards(&equation, ADD_AND_MULTIPLY));
+    assert_eq!(
+        parse_equation_list::<NumberType>("1208925819614629174706176: 1099511627776 1099511627776"),
+        [Equation {
+            test_value: 1 << 80,
+            numbers: vec![1 << 40, 1 << 40]
+        }]
+    );
+}
+
+#[test]
+fn test_evaluation_modes() {
+    let equation = Equation {
+        test_value: 3267,
+        numbers: vec![81, 40, 27],
+    };
+
+    let expression =
+        find_expression(&equation, ADD_AND_MULTIPLY, EvaluationMode::LeftToRight).unwrap();
+    assert_eq!(expression.to_string(), "(81 + 40) * 27");
+
+    let expression =
+        find_expression(&equation, ADD_AND_MULTIPLY, EvaluationMode::Precedence).unwrap();
+    assert_eq!(expression.to_string(), "81 * 40 + 27");
+    assert_eq!(expression.evaluate(), Some(3267));
+
+    // 3 + 9 + 2 * 6, which doesn't work from left to right
+    let equation: Equation = Equation {
+        test_value: 24,
+        numbers: vec![3, 9, 2, 6],
+    };
+    for (mode, is_valid) in [
+        (EvaluationMode::LeftToRight, false),
+        (EvaluationMode::Precedence, true),
+        (EvaluationMode::AnyParenthesisation, true),
+    ] {
+        let expression = find_expression(&equation, &[&Add, &Subtract, &Multiply], mode);
+
+        assert_eq!(expression.is_some(), is_valid, "{mode:?}");
+    }
+
+    // concatenation binds tightest
+    let equation = Equation {
+        test_value: 46,
+        numbers: vec![1, 2, 3, 4],
+    };
+    assert!(find_expression(
+        &equation,
+        ADD_MULTIPLY_AND_CONCATENATE,
+        EvaluationMode::LeftToRight
+    )
+    .is_none());
+    let expression = find_expression(
+        &equation,
+        ADD_MULTIPLY_AND_CONCATENATE,
+        EvaluationMode::Precedence,
+    )
+    .unwrap();
+    assert_eq!(expression.to_string(), "1 || 2 + 3 || 4");
+
+    // signed numbers, where only 1 - (2 - 3) reaches the test value
+    let equation: Equation<i64> = Equation {
+        test_value: 2,
+        numbers: vec![1, 2, 3],
+    };
+    let operators: &OperatorSet<i64> = &[&Subtract];
+    assert!(find_expression(&equation, operators, EvaluationMode::LeftToRight).is_none());
+    let expression =
+        find_expression(&equation, operators, EvaluationMode::AnyParenthesisation).unwrap();
+    assert_eq!(expression.to_string(), "1 - (2 - 3)");
+    assert_eq!(expression.evaluate(), Some(2));
+}
+
+#[test]
+fn test_24_game() {
+    const BASIC_ARITHMETIC: &OperatorSet = &[&Add, &Subtract, &Multiply, &Divide];
+
+    // (7 + 5) * (4 - 2), which only works with parentheses
+    let equation = Equation {
+        test_value: 24,
+        numbers: vec![7, 5, 4, 2],
+    };
+    assert!(find_expression(&equation, BASIC_ARITHMETIC, EvaluationMode::Precedence).is_none());
+
+    let expression = find_expression(
+        &equation,
+        BASIC_ARITHMETIC,
+        EvaluationMode::AnyParenthesisation,
+    )
+    .unwrap();
+    assert_eq!(expression.evaluate(), Some(24));
+    println!("{expression} = 24");
+
+    // the numbers may be used in any order in the actual game
+    let solvable_orders = UniquePermutations::new(vec![1, 3, 4, 6])
+        .filter(|numbers| {
+            let equation = Equation {
+                test_value: 24,
+                numbers: numbers.clone(),
+            };
+
+            find_expression(
+                &equation,
+                BASIC_ARITHMETIC,
+                EvaluationMode::AnyParenthesisation,
+            )
+            .is_some()
+        })
+        .count();
+    assert!(solvable_orders > 0);
+}
+
+#[test]
+fn test_reachable_values() {
+    let reachable = reachable_values(&[6, 8, 6, 15], ADD_MULTIPLY_AND_CONCATENATE, None);
+
+    assert_eq!(reachable.values().sum::<u128>(), 27);
+    assert_eq!(reachable.get(&7290), Some(&1));
+    assert_eq!(reachable.first_key_value(), Some((&35, &1)));
+
+    // 1 + 1 and 1 * 2 both lead to 2 and are only continued once
+    let reachable = reachable_values(&[1, 1, 2], ADD_AND_MULTIPLY, None);
+    assert_eq!(
+        reachable.into_iter().collect::<Vec<_>>(),
+        [(2, 1), (3, 1), (4, 2)]
+    );
+
+    let reachable = reachable_values(&[6, 8, 6, 15], ADD_MULTIPLY_AND_CONCATENATE, Some(100));
+    assert!(reachable.keys().all(|&value| value <= 100));
+    assert!(reachable.contains_key(&35));
+
+    // 3^41 sequences don't fit into a u64, and signed numbers keep every result defined
+    let operators: &OperatorSet<i64> = &[&Add, &Multiply, &Subtract];
+    let reachable = reachable_values(&[1_i64; 42], operators, None);
+    assert_eq!(reachable.values().sum::<u128>(), 3_u128.pow(41));
+    assert_eq!(
+        reachable.last_key_value().map(|(&value, _)| value),
+        Some(42)
+    );
+}
+
+#[test]
+fn test_reachable_values_match_solution_counts() {
+    for equation in parse_equation_list(include_str!("part7-input.txt"))
+        .iter()
+        .take(50)
+    {
+        let reachable = reachable_values(
+            &equation.numbers,
+            ADD_MULTIPLY_AND_CONCATENATE,
+            Some(equation.test_value),
+        );
+
+        assert_eq!(
+            reachable
+                .get(&equation.test_value)
+                .copied()
+                .unwrap_or_default(),
+            count_solutions(equation, ADD_MULTIPLY_AND_CONCATENATE) as u128,
+            "{equation:?}"
+        );
+    }
+}
+
+#[test]
+fn test_unique_permutations() {
+    let permutations = UniquePermutations::new(vec![2, 1, 2]).collect::<Vec<_>>();
+    assert_eq!(permutations, [[1, 2, 2], [2, 1, 2], [2, 2, 1]]);
+
+    for elements in [
+        vec![1],
+        vec![3, 1, 2],
+        vec![1, 1, 1, 1],
+        vec![4, 2, 4, 1, 2, 4],
+        vec![5, 3, 5, 3, 1, 1, 2],
+    ] {
+        let recursive = RecursiveUniquePermutations::new(elements.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            UniquePermutations::new(elements).collect::<Vec<_>>(),
+            recursive
+        );
+    }
+
+    // there is exactly one way to order nothing
+    assert_eq!(
+        UniquePermutations::<u8>::new(vec![]).collect::<Vec<_>>(),
+        [Vec::<u8>::new()]
+    );
+}
+
+#[test]
+fn test_unique_permutations_size_hint() {
+    // 7! / (2! * 2! * 2!)
+    let mut permutations = UniquePermutations::new(vec![5, 3, 5, 3, 1, 1, 2]);
+    assert_eq!(permutations.size_hint(), (630, Some(630)));
+
+    permutations.nth(29);
+    assert_eq!(permutations.size_hint(), (600, Some(600)));
+    assert_eq!(permutations.count(), 600);
+
+    // 20! / (10! * 10!) would overflow if the factorials were computed first
+    let halves = [[0; 10], [1; 10]].concat();
+    assert_eq!(
+        UniquePermutations::new(halves).size_hint(),
+        (184_756, Some(184_756))
+    );
+    assert_eq!(
+        UniquePermutations::new((0..20).collect()).size_hint(),
+        (2_432_902_008_176_640_000, Some(2_432_902_008_176_640_000))
+    );
+
+    // 21! doesn't fit, but they can still be walked through
+    let mut permutations = UniquePermutations::new((0..21).rev().collect());
+    assert_eq!(permutations.size_hint(), (usize::MAX, None));
+    assert_eq!(permutations.next(), Some((0..21).collect()));
+    assert_eq!(permutations.next(), Some((0..19).chain([20, 19]).collect()));
+}
+
+#[test]
+fn test_lending_unique_permutations() {
+    let mut permutations = UniquePermutations::new(vec![4, 2, 4, 1, 2, 4]);
+
+    // walking through all of them rearranges the same buffer over and over
+    let (leading_ones, allocations) = count_allocations(|| {
+        let mut leading_ones = 0;
+        while let Some(permutation) = permutations.next_slice() {
+            if permutation[0] == 1 {
+                leading_ones += 1;
+            }
+        }
+        leading_ones
+    });
+    assert_eq!(allocations, 0);
+    // 5! / (3! * 2!) orders of the rest
+    assert_eq!(leading_ones, 10);
+
+    assert_eq!(permutations.next_slice(), None);
+    assert_eq!(permutations.size_hint(), (0, Some(0)));
+}
+
+#[test]
+fn test_k_permutations() {
+    let permutations = KPermutations::new(vec![2, 1, 2], 2).collect::<Vec<_>>();
+    assert_eq!(permutations, [[1, 2], [2, 1], [2, 2]]);
+
+    // picking everything is the same as permuting everything
+    let elements = vec![5, 3, 5, 3, 1, 1, 2];
+    assert!(KPermutations::new(elements.clone(), elements.len())
+        .eq(UniquePermutations::new(elements.clone())));
+
+    for k in 0..=elements.len() {
+        let permutations = KPermutations::new(elements.clone(), k);
+        let (expected_count, _) = permutations.size_hint();
+
+        let all = permutations.collect::<Vec<_>>();
+        assert_eq!(all.len(), expected_count);
+        assert!(all.is_sorted(), "not in lexicographic order for k = {k}");
+        assert!(all.windows(2).all(|pair| pair[0] != pair[1]));
+    }
+
+    // 4 * 3 * 2 with three different values, 3 * 3 * 3 with one of 1, 3 and 5 twice
+    assert_eq!(KPermutations::new(elements, 3).size_hint(), (51, Some(51)));
+
+    // 30! / 10! doesn't fit
+    let mut permutations = KPermutations::new((0..30).collect(), 20);
+    assert_eq!(permutations.size_hint(), (usize::MAX, None));
+    assert_eq!(permutations.next(), Some((0..20).collect()));
+    assert_eq!(permutations.next(), Some((0..19).chain([20]).collect()));
+}
+
+#[test]
+fn test_combinations() {
+    let combinations = Combinations::new(vec!['a', 'b', 'c', 'd'], 2)
+        .map(String::from_iter)
+        .collect::<Vec<_>>();
+    assert_eq!(combinations, ["ab", "ac", "ad", "bc", "bd", "cd"]);
+
+    let combinations = Combinations::with_repetition(vec!['a', 'b', 'c'], 2)
+        .map(String::from_iter)
+        .collect::<Vec<_>>();
+    assert_eq!(combinations, ["aa", "ab", "ac", "bb", "bc", "cc"]);
+
+    assert_eq!(
+        Combinations::new((0..10).collect(), 4).size_hint(),
+        (210, Some(210))
+    );
+    assert_eq!(Combinations::new((0..10).collect(), 4).count(), 210);
+    assert_eq!(
+        Combinations::with_repetition((0..10).collect(), 4).count(),
+        715
+    );
+
+    assert_eq!(Combinations::new(vec![1, 2], 3).count(), 0);
+    assert_eq!(
+        Combinations::new(vec![1, 2], 0).collect::<Vec<_>>(),
+        [vec![]]
+    );
+    assert_eq!(Combinations::<u8>::with_repetition(vec![], 1).count(), 0);
+
+    // 200 choose 100 doesn't fit
+    let mut combinations = Combinations::new((0..200).collect(), 100);
+    assert_eq!(combinations.size_hint(), (usize::MAX, None));
+    assert_eq!(combinations.next(), Some((0..100).collect()));
+    assert_eq!(combinations.next(), Some((0..99).chain([100]).collect()));
+}
+
+#[test]
+fn test_cartesian_power_in_gray_code_order() {
+    let mut sequences = CartesianPower::new(vec![0, 1, 2], 3, SequenceOrder::GrayCode);
+    assert_eq!(sequences.size_hint(), (27, Some(27)));
+
+    let mut previous: Option<Vec<u8>> = None;
+    let mut seen = Vec::new();
+
+    while let Some((changed_position, sequence)) = sequences.next_change() {
+        match &previous {
+            None => assert_eq!(changed_position, None),
+            Some(previous) => {
+                let differences = (0..3)
+                    .filter(|&position| previous[position] != sequence[position])
+                    .collect::<Vec<_>>();
+                assert_eq!(differences.len(), 1);
+                assert_eq!(changed_position, Some(differences[0]));
+                assert_eq!(
+                    previous[differences[0]].abs_diff(sequence[differences[0]]),
+                    1
+                );
+            }
+        }
+
+        previous = Some(sequence.to_vec());
+        seen.push(sequence.to_vec());
+    }
+
+    seen.sort();
+    let lexicographic =
+        CartesianPower::new(vec![0, 1, 2], 3, SequenceOrder::Lexicographic).collect::<Vec<_>>();
+    assert_eq!(seen, lexicographic);
+
+    assert_eq!(
+        CartesianPower::<u8>::new(vec![], 0, SequenceOrder::GrayCode).count(),
+        1
+    );
+    assert_eq!(
+        CartesianPower::<u8>::new(vec![], 2, SequenceOrder::GrayCode).count(),
+        0
+    );
+
+    // 3^50 doesn't fit
+    let mut sequences = CartesianPower::new(vec![0, 1, 2], 50, SequenceOrder::GrayCode);
+    assert_eq!(sequences.size_hint(), (usize::MAX, None));
+    assert_eq!(sequences.next_change(), Some((None, &[0; 50][..])));
+    assert_eq!(sequences.next_change().unwrap().0, Some(49));
+}
+
+#[test]
+fn test_incremental_evaluation_in_gray_code_order() {
+    let equations = parse_equation_list(include_str!("part7-input.txt"));
+
+    for equation in equations.iter().take(50) {
+        let (first_number, numbers) = equation.numbers.split_first().unwrap();
+        let mut sequences = CartesianPower::new(
+            ADD_MULTIPLY_AND_CONCATENATE.to_vec(),
+            numbers.len(),
+            SequenceOrder::GrayCode,
+        );
+
+        // partial_results[i] is the result after applying the first i operators, only the ones
+        // from the changed position onwards have to be recomputed
+        let mut partial_results = vec![None; numbers.len() + 1];
+        partial_results[0] = Some(*first_number);
+        let mut solutions = 0;
+
+        while let Some((changed_position, operators)) = sequences.next_change() {
+            for position in changed_position.unwrap_or(0)..numbers.len() {
+                partial_results[position + 1] = partial_results[position]
+                    .and_then(|result| operators[position].apply(result, numbers[position]));
+            }
+
+            if partial_results[numbers.len()] == Some(equation.test_value) {
+                solutions += 1;
+            }
+        }
+
+        assert_eq!(
+            solutions,
+            count_solutions(equation, ADD_MULTIPLY_AND_CONCATENATE),
+            "{equation:?}"
+        );
+    }
+}
+
+#[test]
+fn test_parse_operator_constraints() {
+    assert_eq!(
+        "".parse::<OperatorConstraints>(),
+        Ok(OperatorConstraints::default())
+    );
+    assert!(
+        "concatenate<=1, multiply=1, add>=2, !multiply@0, pattern=add multiply"
+            .parse::<OperatorConstraints>()
+            .is_ok()
+    );
+
+    assert!("multiply".parse::<OperatorConstraints>().is_err());
+    assert!("multiply=one".parse::<OperatorConstraints>().is_err());
+    assert!("!multiply".parse::<OperatorConstraints>().is_err());
+    assert!("pattern=".parse::<OperatorConstraints>().is_err());
+
+    // misspelled operators would never match anything
+    assert_eq!(
+        "add>=1, mutliply=1".parse::<OperatorConstraints>(),
+        Err("unknown operator \"mutliply\" in \"mutliply=1\"".to_string())
+    );
+    assert_eq!(
+        "!concat@0".parse::<OperatorConstraints>(),
+        Err("unknown operator \"concat\" in \"!concat@0\"".to_string())
+    );
+    assert_eq!(
+        "pattern=add modulo".parse::<OperatorConstraints>(),
+        Err("unknown operator \"modulo\" in \"pattern=add modulo\"".to_string())
+    );
+}
+
+#[test]
+fn test_constrained_solutions() {
+    let equations = parse_equation_list(include_str!("part7-input.txt"));
+
+    // what the constraints mean, checked on complete sequences only
+    let satisfies = |names: &[&str], description: &str| {
+        let count = |name| names.iter().filter(|&&other| other == name).count();
+
+        match description {
+            "concatenate<=1" => count("concatenate") <= 1,
+            "multiply=1" => count("multiply") == 1,
+            "add>=3, !concatenate@0" => count("add") >= 3 && names.first() != Some(&"concatenate"),
+            "pattern=concatenate add multiply" => names
+                .windows(3)
+                .any(|window| window == ["concatenate", "add", "multiply"]),
+            _ => unreachable!(),
+        }
+    };
+
+    for description in [
+        "concatenate<=1",
+        "multiply=1",
+        "add>=3, !concatenate@0",
+        "pattern=concatenate add multiply",
+    ] {
+        let constraints = description.parse::<OperatorConstraints>().unwrap();
+
+        for equation in equations.iter().take(100) {
+            let constrained = find_all_constrained_solutions(
+                equation,
+                ADD_MULTIPLY_AND_CONCATENATE,
+                &constraints,
+            )
+            .into_iter()
+            .map(|solution| solution.to_string())
+            .collect::<Vec<_>>();
+
+            let filtered = find_all_solutions(equation, ADD_MULTIPLY_AND_CONCATENATE)
+                .into_iter()
+                .filter(|solution| {
+                    let names = solution
+                        .operators
+                        .iter()
+                        .map(|operator| operator.name())
+                        .collect::<Vec<_>>();
+                    satisfies(&names, description)
+                })
+                .map(|solution| solution.to_string())
+                .collect::<Vec<_>>();
+
+            assert_eq!(constrained, filtered, "{description} on {equation:?}");
+        }
+    }
+
+    // 3267: 81 40 27 has two solutions, but only one of them multiplies first
+    let equation = Equation {
+        test_value: 3267,
+        numbers: vec![81, 40, 27],
+    };
+    let constraints = "!add@0".parse().unwrap();
+    let solutions = find_all_constrained_solutions(&equation, ADD_AND_MULTIPLY, &constraints);
+    assert_eq!(solutions.len(), 1);
+    assert_eq!(solutions[0].to_string(), "81 * 40 + 27 = 3267");
+
+    // a single number can't contain any operator
+    let equation = Equation {
+        test_value: 7,
+        numbers: vec![7],
+    };
+    let constraints = "add>=1".parse().unwrap();
+    assert!(find_all_constrained_solutions(&equation, ADD_AND_MULTIPLY, &constraints).is_empty());
+}
+
+#[test]
+fn test_constraints_prune_during_search() {
+    // counts how often operators are applied during a search
+    struct CountingOperator<'a> {
+        inner: &'a dyn Operator,
+        applications: &'a AtomicUsize,
+    }
+
+    impl Operator for CountingOperator<'_> {
+        fn name(&self) -> &'static str {
+            self.inner.name()
+        }
+
+        fn symbol(&self) -> &'static str {
+            self.inner.symbol()
+        }
+
+        fn apply(&self, left: NumberType, right: NumberType) -> Option<NumberType> {
+            self.applications.fetch_add(1, Ordering::Relaxed);
+            self.inner.apply(left, right)
+        }
+    }
+
+    let applications = AtomicUsize::new(0);
+    let add = CountingOperator {
+        inner: &Add,
+        applications: &applications,
+    };
+    let multiply = CountingOperator {
+        inner: &Multiply,
+        applications: &applications,
+    };
+    let operators: &OperatorSet = &[&add, &multiply];
+
+    // unreachable, and the counting operators don't promise to never decrease, so without
+    // constraints every sequence is tried
+    let equation = Equation {
+        test_value: 1,
+        numbers: vec![2; 11],
+    };
+
+    find_all_constrained_solutions(&equation, operators, &OperatorConstraints::default());
+    let unconstrained_applications = applications.swap(0, Ordering::Relaxed);
+    // 2 + 4 + ... + 1024
+    assert_eq!(unconstrained_applications, 2046);
+
+    let constraints = "multiply<=1".parse().unwrap();
+    find_all_constrained_solutions(&equation, operators, &constraints);
+    let constrained_applications = applications.swap(0, Ordering::Relaxed);
+    // only the 11 sequences with at most one multiplication are followed to the end
+    assert!(
+        constrained_applications < 200,
+        "{constrained_applications} applications"
+    );
+}
+
+#[test]
+fn test_signed_numbers() {
+    const WITH_SUBTRACTION: &OperatorSet<i64> = &[&Add, &Multiply, &Concatenate, &Subtract];
+
+    let equations = parse_equation_list::<i64>("-7: 3 10\n2: 3 4 -5\n-123: -12 3");
+    assert_eq!(
+        equations[0],
+        Equation {
+            test_value: -7,
+            numbers: vec![3, 10],
+        }
+    );
+
+    // 3 - 10
+    assert!(is_valid_equation(&equations[0], WITH_SUBTRACTION));
+    assert!(!is_valid_equation(&equations[0], &[&Add, &Multiply]));
+
+    // 3 + 4 + -5, where the intermediate result 7 is above the test value
+    let add_and_multiply: &OperatorSet<i64> = &[&Add, &Multiply];
+    let add_multiply_and_concatenate: &OperatorSet<i64> = &[&Add, &Multiply, &Concatenate];
+
+    for operators in [add_and_multiply, add_multiply_and_concatenate] {
+        assert!(is_valid_equation(&equations[1], operators));
+        assert!(is_valid_equation_backwards(&equations[1], operators));
+        assert_eq!(
+            find_all_solutions(&equations[1], operators)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["3 + 4 + -5 = 2"]
+        );
+    }
+
+    // -12 || 3
+    assert!(is_valid_equation(&equations[2], WITH_SUBTRACTION));
+    assert!(is_valid_equation_backwards(&equations[2], WITH_SUBTRACTION));
+
+    assert_eq!(Concatenate.apply(-12_i64, 3), Some(-123));
+    assert_eq!(Concatenate.apply(12_i64, -3), None);
+    assert_eq!(Concatenate.apply(0_i64, 3), Some(3));
+    assert_eq!(Concatenate.unapply(-123_i64, 3), Some(-12));
+    assert_eq!(Concatenate.unapply(-123_i64, 23), Some(-1));
+    assert_eq!(Concatenate.unapply(-3_i64, 3), None);
+    assert_eq!(Concatenate.unapply(123_i64, -3), None);
+    assert_eq!(Multiply.unapply(-12_i64, -4), Some(3));
+    assert_eq!(Multiply.unapply(i64::MIN, -1), None);
+}
+
+#[test]
+fn test_backwards_solver_matches_forwards_solver_with_signed_numbers() {
+    const WITH_SUBTRACTION: &OperatorSet<i64> = &[&Add, &Multiply, &Concatenate, &Subtract];
+
+    // numbers between -9 and 9
+    let mut random = PseudoRandom::new(7);
+    let mut next_number = || random.below(19) as i64 - 9;
+
+    let mut valid_equations = 0;
+
+    for _ in 0..2000 {
+        let numbers = (0..5).map(|_| next_number()).collect::<Vec<_>>();
+
+        // build the test value from random operators most of the time, so that there are enough
+        // valid equations
+        let mut test_value = numbers[0];
+        for &number in &numbers[1..] {
+            let operator = WITH_SUBTRACTION[next_number().unsigned_abs() as usize % 4];
+            // negative numbers can't be concatenated
+            test_value = operator
+                .apply(test_value, number)
+                .unwrap_or(test_value + number);
+        }
+        if next_number() > 5 {
+            test_value += next_number();
+        }
+
+        let equation = Equation {
+            test_value,
+            numbers,
+        };
+
+        let forwards = is_valid_equation(&equation, WITH_SUBTRACTION);
+        assert_eq!(
+            forwards,
+            is_valid_equation_backwards(&equation, WITH_SUBTRACTION),
+            "{equation:?}"
+        );
+        assert_eq!(
+            forwards,
+            !find_all_solutions(&equation, WITH_SUBTRACTION).is_empty(),
+            "{equation:?}"
+        );
+
+        if forwards {
+            valid_equations += 1;
+        }
+    }
+
+    assert!(
+        valid_equations > 1000,
+        "only {valid_equations} valid equations"
+    );
+}
+
+/// A number that lives on the heap and can't be copied, like a big integer would.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+struct HeapNumber(Box<i128>);
+
+impl fmt::Display for HeapNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for HeapNumber {
+    type Err = <i128 as FromStr>::Err;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(Box::new(s.parse()?)))
+    }
+}
+
+impl Number for HeapNumber {
+    fn from_u8(value: u8) -> Self {
+        Self(Box::new(value.into()))
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(Self(Box::new(self.0.checked_add(*other.0)?)))
+    }
+
+    fn checked_sub(&self, other: &Self) -> Option<Self> {
+        Some(Self(Box::new(self.0.checked_sub(*other.0)?)))
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        Some(Self(Box::new(self.0.checked_mul(*other.0)?)))
+    }
+
+    fn checked_div(&self, other: &Self) -> Option<Self> {
+        Some(Self(Box::new(self.0.checked_div(*other.0)?)))
+    }
+
+    fn checked_rem(&self, other: &Self) -> Option<Self> {
+        Some(Self(Box::new(self.0.checked_rem(*other.0)?)))
+    }
+
+    fn checked_pow(&self, exponent: &Self) -> Option<Self> {
+        Some(Self(Box::new(
+            self.0.checked_pow(u32::try_from(*exponent.0).ok()?)?,
+        )))
+    }
+
+    fn bit_xor(&self, other: &Self) -> Self {
+        Self(Box::new(*self.0 ^ *other.0))
+    }
+}
+
+#[test]
+fn test_number_types_agree() {
+    fn sum_of_valid_equations<N: Number>(input: &str, operators: &OperatorSet<N>) -> String {
+        parse_equation_list::<N>(input)
+            .into_iter()
+            .filter(|equation| is_valid_equation_backwards(equation, operators))
+            .fold(N::zero(), |sum, equation| {
+                sum.checked_add(&equation.test_value).unwrap()
+            })
+            .to_string()
+    }
+
+    let input = include_str!("part7-input.txt");
+
+    assert_eq!(
+        sum_of_valid_equations::<u64>(input, &[&Add, &Multiply, &Concatenate]),
+        "105517128211543"
+    );
+    assert_eq!(
+        sum_of_valid_equations::<i64>(input, &[&Add, &Multiply, &Concatenate]),
+        "105517128211543"
+    );
+    assert_eq!(
+        sum_of_valid_equations::<u128>(input, &[&Add, &Multiply, &Concatenate]),
+        "105517128211543"
+    );
+    assert_eq!(
+        sum_of_valid_equations::<HeapNumber>(input, &[&Add, &Multiply, &Concatenate]),
+        "105517128211543"
+    );
+}
+
+#[test]
+fn test_backwards_solver_falls_back_for_operators_without_unapply() {
+    // the same as `Add`, but it can't be undone
+    struct ForwardsAdd;
+
+    impl Operator for ForwardsAdd {
+        fn name(&self) -> &'static str {
+            "add"
+        }
+
+        fn symbol(&self) -> &'static str {
+            "+"
+        }
+
+        fn apply(&self, left: NumberType, right: NumberType) -> Option<NumberType> {
+            left.checked_add(right)
+        }
+    }
+
+    // can be undone, but doesn't offer itself for it
+    struct ForgetfulAdd;
+
+    impl Operator for ForgetfulAdd {
+        fn name(&self) -> &'static str {
+            "add"
+        }
+
+        fn symbol(&self) -> &'static str {
+            "+"
+        }
+
+        fn apply(&self, left: NumberType, right: NumberType) -> Option<NumberType> {
+            left.checked_add(right)
+        }
+    }
+
+    impl InvertibleOperator for ForgetfulAdd {
+        fn unapply(&self, result: NumberType, right: NumberType) -> Option<NumberType> {
+            result.checked_sub(right)
+        }
+    }
+
+    for test_value in [5, 11, 12, 30] {
+        let equation = Equation {
+            test_value,
+            numbers: vec![2, 3, 6],
+        };
+
+        for operators in [
+            &[&ForwardsAdd as &dyn Operator, &Multiply] as &OperatorSet,
+            &[&ForgetfulAdd, &Multiply],
+            ADD_AND_MULTIPLY,
+        ] {
+            assert_eq!(
+                is_valid_equation_backwards(&equation, operators),
+                is_valid_equation(&equation, operators),
+                "{equation:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_long_equations() {
+    // longer than what fits into the search stack on the stack, and with a zero, which makes the
+    // backwards solver fall back to the forwards search
+    let mut numbers = vec![1; 40];
+    numbers[20] = 0;
+
+    let equation = Equation {
+        test_value: 39,
+        numbers,
+    };
+
+    for operators in [ADD_AND_MULTIPLY, ADD_MULTIPLY_AND_CONCATENATE] {
+        assert!(is_valid_equation(&equation, operators));
+        assert!(is_valid_equation_backwards(&equation, operators));
+    }
 }
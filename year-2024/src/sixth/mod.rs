@@ -1,6 +1,13 @@
-use core::{fmt, panic};
+use core::fmt;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use statistics::PatrolStatistics;
 use std::collections::HashSet;
 
+use crate::progress::{progress_for, Progress};
+
+mod obstacles;
+mod serialisation;
+mod statistics;
 #[cfg(test)]
 mod tests;
 
@@ -17,6 +24,8 @@ const SAMPLE: &str = "
 #.........
 ......#...";
 
+type Position = (usize, usize);
+
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 enum Direction {
     Upwards,
@@ -41,6 +50,30 @@ impl fmt::Display for Direction {
 }
 
 impl Direction {
+    /// Character of the guard facing this direction in the puzzle input.
+    fn to_ascii(self) -> char {
+        use Direction::*;
+
+        match self {
+            Upwards => '^',
+            Right => '>',
+            Downwards => 'v',
+            Left => '<',
+        }
+    }
+
+    fn from_ascii(character: char) -> Option<Direction> {
+        use Direction::*;
+
+        match character {
+            '^' => Some(Upwards),
+            '>' => Some(Right),
+            'v' => Some(Downwards),
+            '<' => Some(Left),
+            _ => None,
+        }
+    }
+
     fn turn_right(&self) -> Direction {
         use Direction::*;
 
@@ -53,13 +86,21 @@ impl Direction {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct Guard {
     position: (usize, usize),
     current_direction: Direction,
 }
 
-#[derive(Clone, Copy, PartialEq)]
+impl Guard {
+    #[cfg(test)]
+    pub fn move_to(&mut self, position: Position, direction: Direction) {
+        self.position = position;
+        self.current_direction = direction;
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
 enum MapTile {
     Obstruction,
     HighlightedObstruction,
@@ -76,21 +117,26 @@ impl fmt::Debug for MapTile {
     }
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct Map {
     size: (usize, usize),
     map: Vec<Vec<MapTile>>,
 }
 
 impl Map {
+    /// Replaces the tile at the given position and returns the previous one.
+    pub fn set_tile(&mut self, (x, y): Position, tile: MapTile) -> MapTile {
+        std::mem::replace(&mut self.map[y][x], tile)
+    }
+
     /// Finds the next obstruction based on the current position.
     /// Returns the position that is exactly one step away before running into the obstruction.
     /// Returns None when you fall outside the world (there is no obstruction on the way to void).
     pub fn get_next_obstruction(
         &self,
-        start_point: (usize, usize),
+        start_point: Position,
         direction: Direction,
-    ) -> (Option<(usize, usize)>, Vec<(usize, usize)>) {
+    ) -> (Option<Position>, Vec<Position>) {
         let mut visited_positions = vec![start_point];
 
         loop {
@@ -142,6 +188,34 @@ impl Map {
     }
 
     pub fn text_representation(&self, guard: Option<&Guard>) -> String {
+        self.text_representation_with_overlay(guard, |_, _| None)
+    }
+
+    /// Like `text_representation`, but free tiles show how often the guard stood on them: `1` to
+    /// `9`, or `+` for ten visits and more.
+    pub fn heatmap_representation(
+        &self,
+        guard: Option<&Guard>,
+        statistics: &PatrolStatistics,
+    ) -> String {
+        self.text_representation_with_overlay(guard, |(x, y), tile| {
+            if tile != MapTile::Free {
+                return None;
+            }
+
+            match statistics.visit_counts[y][x] {
+                0 => None,
+                count @ 1..=9 => char::from_digit(count, 10),
+                _ => Some('+'),
+            }
+        })
+    }
+
+    fn text_representation_with_overlay(
+        &self,
+        guard: Option<&Guard>,
+        overlay: impl Fn(Position, MapTile) -> Option<char>,
+    ) -> String {
         let mut map_representation = String::new();
 
         for (y, rows) in self.map.iter().enumerate() {
@@ -155,7 +229,10 @@ impl Map {
                     }
                 }
 
-                map_representation.push_str(&format!("{tile:?}"));
+                match overlay((x, y), *tile) {
+                    Some(overlay_character) => map_representation.push(overlay_character),
+                    None => map_representation.push_str(&format!("{tile:?}")),
+                }
             }
 
             map_representation.push('\n');
@@ -175,9 +252,53 @@ pub(crate) fn sixth_december() {
 
     dbg!(part_1(&map, guard.clone()));
 
-    let locations_for_looping_the_security = part_2(map, guard);
+    let patrol_statistics = statistics::patrol_statistics(&map, guard.clone());
+
+    // `--heatmap` shows how often the guard stood on each tile
+    if std::env::args().any(|argument| argument == "--heatmap") {
+        println!(
+            "{}",
+            map.heatmap_representation(Some(&guard), &patrol_statistics)
+        );
+    }
+
+    dbg!(
+        patrol_statistics.visited_tiles(),
+        patrol_statistics.turns,
+        patrol_statistics.longest_straight_run,
+        patrol_statistics.exit,
+        patrol_statistics.distinct_states
+    );
+
+    let locations_for_looping_the_security =
+        part_2(map.clone(), guard.clone(), progress_for("sixth").as_ref());
 
     dbg!(locations_for_looping_the_security.len());
+
+    let pairs_of_obstructions_for_looping_the_security =
+        obstacles::find_trapping_obstructions(&map, &guard, 2);
+
+    dbg!(pairs_of_obstructions_for_looping_the_security.len());
+
+    let (fewest_trapping_obstructions, ways_to_trap_with_fewest) =
+        obstacles::minimum_trapping_obstructions(&map, &guard, 2).unwrap_or_default();
+
+    dbg!(fewest_trapping_obstructions, ways_to_trap_with_fewest.len());
+
+    // the existing obstructions that let the guard escape again from the first trap
+    if let Some(&position) = locations_for_looping_the_security.first() {
+        let mut trap_map = map.clone();
+        trap_map.set_tile(position, MapTile::HighlightedObstruction);
+
+        let escape_obstructions = obstacles::find_escape_obstructions(&trap_map, &guard);
+        dbg!(escape_obstructions.len());
+
+        // `--trap` prints that map in the input format, with the added obstruction as `$`
+        if std::env::args().any(|argument| argument == "--trap") {
+            let guards = std::slice::from_ref(&guard);
+            print!("{}", serialisation::serialise_map(&trap_map, guards));
+        }
+    }
 }
 
 // Checks whether it is allowed to place an obstacle at the candidate's position
@@ -200,62 +321,71 @@ fn is_valid_position_for_obstacle((x, y): (usize, usize), map: &Map, guard: &Gua
     true
 }
 
-fn part_2(map: Map, guard: Guard) -> Vec<(usize, usize)> {
-    let mut possible_obstacle_positions_to_create_loops = Vec::new();
+fn part_2(map: Map, guard: Guard, progress: &dyn Progress) -> Vec<(usize, usize)> {
+    progress.start(map.size.0);
 
-    for x in 0..map.size.0 {
-        'outer: for y in 0..map.size.1 {
-            if !is_valid_position_for_obstacle((x, y), &map, &guard) {
-                continue;
-            }
+    // every column is checked on its own, collecting keeps the columns in order
+    let possible_obstacle_positions_to_create_loops = (0..map.size.0)
+        .into_par_iter()
+        .flat_map_iter(|x| {
+            let mut possible_obstacle_positions_in_column = Vec::new();
 
-            let mut map_with_obstruction = map.clone();
+            for y in 0..map.size.1 {
+                if !is_valid_position_for_obstacle((x, y), &map, &guard) {
+                    continue;
+                }
 
-            // place an obstacle and check whether we run into a loop
-            map_with_obstruction.map[y][x] = MapTile::HighlightedObstruction;
+                let mut map_with_obstruction = map.clone();
 
-            let mut guard_on_new_map = guard.clone();
+                // place an obstacle and check whether we run into a loop
+                map_with_obstruction.map[y][x] = MapTile::HighlightedObstruction;
 
-            let mut positions_with_took_turns: HashSet<((usize, usize), Direction)> =
-                HashSet::new();
+                if guard_runs_into_loop(&map_with_obstruction, guard.clone()) {
+                    // println!(
+                    //     "found new position for obstacle at ({x}, {y}, guard_pos={:?}):",
+                    //     guard.position
+                    // );
+                    // println!("{}", map_with_obstruction.text_representation(Some(&guard)));
+                    // println!();
 
-            loop {
-                match map_with_obstruction.get_next_obstruction(
-                    guard_on_new_map.position,
-                    guard_on_new_map.current_direction,
-                ) {
-                    (Some(new_position), _walked_path) => {
-                        let in_loop = !positions_with_took_turns
-                            .insert((new_position, guard_on_new_map.current_direction));
+                    possible_obstacle_positions_in_column.push((x, y));
+                }
+            }
 
-                        if in_loop {
-                            // println!(
-                            //     "found new position for obstacle at ({x}, {y}, guard_pos={:?}):",
-                            //     guard.position
-                            // );
-                            // println!("{}", map_with_obstruction.text_representation(Some(&guard)));
-                            // println!();
+            progress.advance(1);
 
-                            possible_obstacle_positions_to_create_loops.push((x, y));
-                            continue 'outer;
-                        }
+            possible_obstacle_positions_in_column
+        })
+        .collect();
 
-                        guard_on_new_map.position = new_position;
-                        guard_on_new_map.current_direction =
-                            guard_on_new_map.current_direction.turn_right();
-                    }
-                    (None, walked_path) => {
-                        guard_on_new_map.position = *walked_path.last().unwrap();
+    progress.finish();
 
-                        // we're not in a loop as we run out of the world
-                        continue 'outer;
-                    }
+    possible_obstacle_positions_to_create_loops
+}
+
+// Checks whether the guard keeps walking in circles instead of leaving the map
+fn guard_runs_into_loop(map: &Map, mut guard: Guard) -> bool {
+    let mut positions_with_took_turns: HashSet<((usize, usize), Direction)> = HashSet::new();
+
+    loop {
+        match map.get_next_obstruction(guard.position, guard.current_direction) {
+            (Some(new_position), _walked_path) => {
+                let in_loop =
+                    !positions_with_took_turns.insert((new_position, guard.current_direction));
+
+                if in_loop {
+                    return true;
                 }
+
+                guard.position = new_position;
+                guard.current_direction = guard.current_direction.turn_right();
+            }
+            (None, _walked_path) => {
+                // we're not in a loop as we run out of the world
+                return false;
             }
         }
     }
-
-    possible_obstacle_positions_to_create_loops
 }
 
 fn part_1(map: &Map, mut guard: Guard) -> usize {
@@ -281,57 +411,9 @@ fn part_1(map: &Map, mut guard: Guard) -> usize {
 }
 
 fn parse_puzzle_input(input: impl AsRef<str>) -> (Map, Guard) {
-    let input = input.as_ref().trim();
-
-    let mut size = (None, 0);
-    let mut guard_position = None;
-
-    let mut map = Vec::new();
-
-    for (y, line) in input.lines().enumerate() {
-        match size.0 {
-            None => size.0 = Some(line.len()),
-            Some(x) => {
-                if x != line.len() {
-                    panic!("map is not square");
-                }
-            }
-        }
-
-        size.1 += 1;
+    let (map, mut guards) = serialisation::parse_map(input);
 
-        let mut row = Vec::with_capacity(line.len());
-        for (x, map_tile) in line.chars().enumerate() {
-            match map_tile {
-                '^' => {
-                    guard_position = Some((x, y));
-                    row.push(MapTile::Free);
-                }
-                '#' => {
-                    row.push(MapTile::Obstruction);
-                }
-                '.' => {
-                    row.push(MapTile::Free);
-                }
-                _other => {
-                    unreachable!();
-                }
-            }
-        }
-
-        debug_assert_eq!(row.len(), line.len());
-
-        map.push(row);
-    }
+    assert_eq!(guards.len(), 1, "expected exactly one guard");
 
-    (
-        Map {
-            size: (size.0.unwrap(), size.1),
-            map,
-        },
-        Guard {
-            current_direction: Direction::Upwards,
-            position: guard_position.unwrap(),
-        },
-    )
+    (map, guards.remove(0))
 }
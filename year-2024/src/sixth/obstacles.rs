@@ -0,0 +1,400 @@
+use std::collections::HashSet;
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use super::{guard_runs_into_loop, Direction, Guard, Map, MapTile, Position};
+
+/// Precomputed stopping points of the guard on a map, so that a patrol can jump from one turn to
+/// the next instead of walking tile by tile. Additional obstructions are checked on the fly.
+struct JumpTable {
+    width: usize,
+    /// Indexed by `(y * width + x) * 4 + direction`. `None` means the guard walks off the map.
+    stops: Vec<Option<Position>>,
+}
+
+fn direction_index(direction: Direction) -> usize {
+    match direction {
+        Direction::Upwards => 0,
+        Direction::Right => 1,
+        Direction::Downwards => 2,
+        Direction::Left => 3,
+    }
+}
+
+fn is_blocked(map: &Map, (x, y): Position) -> bool {
+    matches!(
+        map.map[y][x],
+        MapTile::Obstruction | MapTile::HighlightedObstruction
+    )
+}
+
+/// Returns how many steps the guard needs to walk from `from` in `direction` to reach `target`,
+/// or None when `target` is not straight ahead.
+fn steps_until(from: Position, direction: Direction, target: Position) -> Option<usize> {
+    match direction {
+        Direction::Upwards if target.0 == from.0 && target.1 < from.1 => Some(from.1 - target.1),
+        Direction::Right if target.1 == from.1 && target.0 > from.0 => Some(target.0 - from.0),
+        Direction::Downwards if target.0 == from.0 && target.1 > from.1 => Some(target.1 - from.1),
+        Direction::Left if target.1 == from.1 && target.0 < from.0 => Some(from.0 - target.0),
+        _ => None,
+    }
+}
+
+fn step_towards(from: Position, direction: Direction, steps: usize) -> Position {
+    match direction {
+        Direction::Upwards => (from.0, from.1 - steps),
+        Direction::Right => (from.0 + steps, from.1),
+        Direction::Downwards => (from.0, from.1 + steps),
+        Direction::Left => (from.0 - steps, from.1),
+    }
+}
+
+impl JumpTable {
+    fn new(map: &Map) -> Self {
+        let (width, height) = map.size;
+        let mut stops = vec![None; width * height * 4];
+
+        for x in 0..width {
+            let mut stop = None;
+            for y in 0..height {
+                if is_blocked(map, (x, y)) {
+                    stop = Some((x, y + 1));
+                } else {
+                    stops[(y * width + x) * 4 + direction_index(Direction::Upwards)] = stop;
+                }
+            }
+
+            let mut stop = None;
+            for y in (0..height).rev() {
+                if is_blocked(map, (x, y)) {
+                    stop = y.checked_sub(1).map(|y| (x, y));
+                } else {
+                    stops[(y * width + x) * 4 + direction_index(Direction::Downwards)] = stop;
+                }
+            }
+        }
+
+        for y in 0..height {
+            let mut stop = None;
+            for x in 0..width {
+                if is_blocked(map, (x, y)) {
+                    stop = Some((x + 1, y));
+                } else {
+                    stops[(y * width + x) * 4 + direction_index(Direction::Left)] = stop;
+                }
+            }
+
+            let mut stop = None;
+            for x in (0..width).rev() {
+                if is_blocked(map, (x, y)) {
+                    stop = x.checked_sub(1).map(|x| (x, y));
+                } else {
+                    stops[(y * width + x) * 4 + direction_index(Direction::Right)] = stop;
+                }
+            }
+        }
+
+        Self { width, stops }
+    }
+
+    /// Returns the position where the guard has to turn, taking the additional obstructions into
+    /// account. Returns None when the guard leaves the map.
+    fn next_stop(
+        &self,
+        position: Position,
+        direction: Direction,
+        additional_obstructions: &[Position],
+    ) -> Option<Position> {
+        let stop =
+            self.stops[(position.1 * self.width + position.0) * 4 + direction_index(direction)];
+        let steps_to_stop = stop.map(|stop| steps_until(position, direction, stop).unwrap_or(0));
+
+        let nearest_additional_obstruction = additional_obstructions
+            .iter()
+            .filter_map(|&obstruction| steps_until(position, direction, obstruction))
+            .filter(|&steps| steps_to_stop.is_none_or(|steps_to_stop| steps <= steps_to_stop))
+            .min();
+
+        match nearest_additional_obstruction {
+            Some(steps) => Some(step_towards(position, direction, steps - 1)),
+            None => stop,
+        }
+    }
+}
+
+/// Walks the guard over the map by jumping from turn to turn. Keeps its scratch buffers around, so
+/// that repeated patrols don't need to allocate.
+struct Patrol<'a> {
+    map: &'a Map,
+    jump_table: &'a JumpTable,
+    start: Guard,
+    /// Generation in which a `(position, direction)` state was last seen.
+    seen_turns: Vec<u32>,
+    /// Generation in which a tile was last visited.
+    seen_tiles: Vec<u32>,
+    generation: u32,
+}
+
+impl<'a> Patrol<'a> {
+    fn new(map: &'a Map, jump_table: &'a JumpTable, start: Guard) -> Self {
+        let tiles = map.size.0 * map.size.1;
+
+        Self {
+            map,
+            jump_table,
+            start,
+            seen_turns: vec![0; tiles * 4],
+            seen_tiles: vec![0; tiles],
+            generation: 0,
+        }
+    }
+
+    fn next_generation(&mut self) -> u32 {
+        self.generation += 1;
+        self.generation
+    }
+
+    fn is_trapped(&mut self, additional_obstructions: &[Position]) -> bool {
+        let generation = self.next_generation();
+        let mut position = self.start.position;
+        let mut direction = self.start.current_direction;
+
+        while let Some(stop) =
+            self.jump_table
+                .next_stop(position, direction, additional_obstructions)
+        {
+            let state = (stop.1 * self.map.size.0 + stop.0) * 4 + direction_index(direction);
+            if self.seen_turns[state] == generation {
+                return true;
+            }
+            self.seen_turns[state] = generation;
+
+            position = stop;
+            direction = direction.turn_right();
+        }
+
+        false
+    }
+
+    /// Returns the tiles the guard walks over in the order they are visited for the first time.
+    /// Returns None when the guard is trapped in a loop.
+    fn first_visits(&mut self, additional_obstructions: &[Position]) -> Option<Vec<Position>> {
+        let generation = self.next_generation();
+        let (width, height) = self.map.size;
+        let mut position = self.start.position;
+        let mut direction = self.start.current_direction;
+        let mut first_visits = vec![position];
+        self.seen_tiles[position.1 * width + position.0] = generation;
+
+        loop {
+            let stop = self
+                .jump_table
+                .next_stop(position, direction, additional_obstructions);
+
+            let steps = match stop {
+                Some(stop) => steps_until(position, direction, stop).unwrap_or(0),
+                None => match direction {
+                    Direction::Upwards => position.1,
+                    Direction::Right => width - 1 - position.0,
+                    Direction::Downwards => height - 1 - position.1,
+                    Direction::Left => position.0,
+                },
+            };
+
+            for step in 1..=steps {
+                let tile = step_towards(position, direction, step);
+                if self.seen_tiles[tile.1 * width + tile.0] != generation {
+                    self.seen_tiles[tile.1 * width + tile.0] = generation;
+                    first_visits.push(tile);
+                }
+            }
+
+            let Some(stop) = stop else {
+                return Some(first_visits);
+            };
+
+            let state = (stop.1 * width + stop.0) * 4 + direction_index(direction);
+            if self.seen_turns[state] == generation {
+                return None;
+            }
+            self.seen_turns[state] = generation;
+
+            position = stop;
+            direction = direction.turn_right();
+        }
+    }
+
+    /// Checks that no proper subset of the obstructions traps the guard already. The prefixes have
+    /// been checked while searching, so only the other subsets are simulated.
+    fn is_minimal(&mut self, obstructions: &[Position]) -> bool {
+        let full_mask = (1u32 << obstructions.len()) - 1;
+        let mut subset = Vec::with_capacity(obstructions.len());
+
+        for mask in 1..full_mask {
+            if (mask + 1).is_power_of_two() {
+                continue;
+            }
+
+            subset.clear();
+            subset.extend(
+                obstructions
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| mask & (1 << i) != 0)
+                    .map(|(_, &obstruction)| obstruction),
+            );
+
+            if self.is_trapped(&subset) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Extends `obstructions` with obstructions the guard runs into after the last one. Candidates
+    /// are taken from `first_visits` starting at `first_candidate`, because any tile the guard
+    /// visited before would have stopped it earlier.
+    fn search(
+        &mut self,
+        obstructions: &mut Vec<Position>,
+        first_visits: &[Position],
+        first_candidate: usize,
+        remaining: usize,
+        found: &mut Vec<Vec<Position>>,
+    ) {
+        for (index, &candidate) in first_visits.iter().enumerate().skip(first_candidate) {
+            obstructions.push(candidate);
+
+            if remaining == 1 {
+                if self.is_trapped(obstructions) && self.is_minimal(obstructions) {
+                    let mut obstruction_set = obstructions.clone();
+                    obstruction_set.sort();
+                    found.push(obstruction_set);
+                }
+            } else if let Some(next_first_visits) = self.first_visits(obstructions) {
+                // when the guard is trapped already, adding more obstructions can't be minimal
+                self.search(
+                    obstructions,
+                    &next_first_visits,
+                    index,
+                    remaining - 1,
+                    found,
+                );
+            }
+
+            obstructions.pop();
+        }
+    }
+}
+
+/// Finds all sets of exactly `count` additional obstructions that trap the guard in a loop, where
+/// no smaller subset would have trapped it already. Each set is sorted, the sets are ordered.
+///
+/// Only tiles the guard actually runs into are considered: the first obstruction has to be on the
+/// original patrol, every further one on the patrol after the previous obstruction was hit.
+pub(super) fn find_trapping_obstructions(
+    map: &Map,
+    guard: &Guard,
+    count: usize,
+) -> Vec<Vec<Position>> {
+    assert!(count > 0 && count < 32, "unsupported obstruction count");
+
+    let jump_table = JumpTable::new(map);
+    let Some(first_visits) = Patrol::new(map, &jump_table, guard.clone()).first_visits(&[]) else {
+        // the guard is trapped without any additional obstruction
+        return Vec::new();
+    };
+
+    let mut found: Vec<Vec<Position>> = (1..first_visits.len())
+        .into_par_iter()
+        .map_init(
+            || Patrol::new(map, &jump_table, guard.clone()),
+            |patrol, index| {
+                let mut found = Vec::new();
+                let mut obstructions = vec![first_visits[index]];
+
+                if count == 1 {
+                    if patrol.is_trapped(&obstructions) {
+                        found.push(obstructions);
+                    }
+                } else if let Some(next_first_visits) = patrol.first_visits(&obstructions) {
+                    patrol.search(
+                        &mut obstructions,
+                        &next_first_visits,
+                        index,
+                        count - 1,
+                        &mut found,
+                    );
+                }
+
+                found
+            },
+        )
+        .flatten()
+        .collect();
+
+    found.sort();
+    found.dedup();
+
+    found
+}
+
+/// Finds the smallest number of additional obstructions (up to `max_count`) that trap the guard,
+/// together with all minimal ways to place them. Returns None if no such placement exists.
+pub(super) fn minimum_trapping_obstructions(
+    map: &Map,
+    guard: &Guard,
+    max_count: usize,
+) -> Option<(usize, Vec<Vec<Position>>)> {
+    if guard_runs_into_loop(map, guard.clone()) {
+        return Some((0, vec![Vec::new()]));
+    }
+
+    (1..=max_count)
+        .map(|count| (count, find_trapping_obstructions(map, guard, count)))
+        .find(|(_, obstruction_sets)| !obstruction_sets.is_empty())
+}
+
+/// Finds the existing obstructions which let a looping guard escape when removed. Returns an empty
+/// list when the guard isn't trapped in the first place.
+pub(super) fn find_escape_obstructions(map: &Map, guard: &Guard) -> Vec<Position> {
+    if !guard_runs_into_loop(map, guard.clone()) {
+        return Vec::new();
+    }
+
+    // only obstructions the guard runs into can change anything about its patrol
+    let mut touched_obstructions = Vec::new();
+    let mut positions_with_took_turns = HashSet::new();
+    let mut position = guard.position;
+    let mut direction = guard.current_direction;
+
+    while let (Some(stop), _walked_path) = map.get_next_obstruction(position, direction) {
+        if !positions_with_took_turns.insert((stop, direction)) {
+            // we walked the whole loop
+            break;
+        }
+
+        let obstruction = step_towards(stop, direction, 1);
+        if !touched_obstructions.contains(&obstruction) {
+            touched_obstructions.push(obstruction);
+        }
+
+        position = stop;
+        direction = direction.turn_right();
+    }
+
+    let mut escape_obstructions: Vec<Position> = touched_obstructions
+        .into_iter()
+        .filter(|&(x, y)| {
+            let mut map_without_obstruction = map.clone();
+            map_without_obstruction.map[y][x] = MapTile::Free;
+
+            !guard_runs_into_loop(&map_without_obstruction, guard.clone())
+        })
+        .collect();
+
+    escape_obstructions.sort();
+
+    escape_obstructions
+}
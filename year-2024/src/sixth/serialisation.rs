@@ -0,0 +1,91 @@
+use super::{Direction, Guard, Map, MapTile};
+
+/// Writes the map in the puzzle's input format, with the guards drawn as `^`, `>`, `v` and `<`.
+/// Unlike `Map::text_representation` the result can be read back with `parse_map`, which returns
+/// the guards in reading order whatever order they are passed in here.
+pub(super) fn serialise_map(map: &Map, guards: &[Guard]) -> String {
+    let mut serialised_map = String::with_capacity((map.size.0 + 1) * map.size.1);
+
+    for (y, row) in map.map.iter().enumerate() {
+        for (x, tile) in row.iter().enumerate() {
+            let mut guards_on_tile = guards.iter().filter(|guard| guard.position == (x, y));
+
+            match guards_on_tile.next() {
+                Some(guard) => {
+                    assert!(
+                        guards_on_tile.next().is_none(),
+                        "only one guard can stand on a tile"
+                    );
+                    assert_eq!(*tile, MapTile::Free, "guards can only stand on free tiles");
+
+                    serialised_map.push(guard.current_direction.to_ascii());
+                }
+                None => serialised_map.push_str(&format!("{tile:?}")),
+            }
+        }
+
+        serialised_map.push('\n');
+    }
+
+    serialised_map
+}
+
+/// Reads a map in the puzzle's input format. Highlighted obstructions are read from `$`, guards
+/// may face any direction and are returned in reading order.
+pub(super) fn parse_map(input: impl AsRef<str>) -> (Map, Vec<Guard>) {
+    let input = input.as_ref().trim();
+
+    let mut size = (None, 0);
+    let mut guards = Vec::new();
+
+    let mut map = Vec::new();
+
+    for (y, line) in input.lines().enumerate() {
+        match size.0 {
+            None => size.0 = Some(line.len()),
+            Some(x) => {
+                if x != line.len() {
+                    panic!("map is not square");
+                }
+            }
+        }
+
+        size.1 += 1;
+
+        let mut row = Vec::with_capacity(line.len());
+        for (x, map_tile) in line.chars().enumerate() {
+            match map_tile {
+                '#' => {
+                    row.push(MapTile::Obstruction);
+                }
+                '$' => {
+                    row.push(MapTile::HighlightedObstruction);
+                }
+                '.' => {
+                    row.push(MapTile::Free);
+                }
+                other => {
+                    let current_direction = Direction::from_ascii(other).unwrap();
+
+                    guards.push(Guard {
+                        position: (x, y),
+                        current_direction,
+                    });
+                    row.push(MapTile::Free);
+                }
+            }
+        }
+
+        debug_assert_eq!(row.len(), line.len());
+
+        map.push(row);
+    }
+
+    (
+        Map {
+            size: (size.0.unwrap(), size.1),
+            map,
+        },
+        guards,
+    )
+}
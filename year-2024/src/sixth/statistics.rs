@@ -0,0 +1,89 @@
+use std::collections::HashSet;
+
+use super::{Direction, Guard, Map, Position};
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct PatrolStatistics {
+    /// How often the guard stood on each tile, indexed like `Map::map` (`[y][x]`).
+    pub(super) visit_counts: Vec<Vec<u32>>,
+    /// Number of right turns the guard took.
+    pub(super) turns: usize,
+    /// Most steps the guard walked without turning.
+    pub(super) longest_straight_run: usize,
+    /// Last tile and direction before the guard left the map. None when the guard is trapped.
+    pub(super) exit: Option<(Position, Direction)>,
+    /// Number of distinct `(position, direction)` states the guard has been in.
+    pub(super) distinct_states: usize,
+}
+
+impl PatrolStatistics {
+    /// Number of distinct tiles the guard walked over, the answer to part 1.
+    pub(super) fn visited_tiles(&self) -> usize {
+        self.visit_counts
+            .iter()
+            .flatten()
+            .filter(|&&count| count > 0)
+            .count()
+    }
+}
+
+/// Follows the guard until it either leaves the map or enters a state it has been in before.
+pub(super) fn patrol_statistics(map: &Map, mut guard: Guard) -> PatrolStatistics {
+    let mut visit_counts = vec![vec![0; map.size.0]; map.size.1];
+    let mut seen_states: HashSet<(Position, Direction)> = HashSet::new();
+    let mut turns = 0;
+    let mut longest_straight_run = 0;
+
+    visit_counts[guard.position.1][guard.position.0] += 1;
+    seen_states.insert((guard.position, guard.current_direction));
+
+    loop {
+        let (next_obstruction, walked_path) =
+            map.get_next_obstruction(guard.position, guard.current_direction);
+
+        longest_straight_run = longest_straight_run.max(walked_path.len() - 1);
+
+        // the first position of the path is where we're already standing
+        for &(x, y) in &walked_path[1..] {
+            if !seen_states.insert(((x, y), guard.current_direction)) {
+                // been here facing the same way before, so we'll walk in circles from now on
+                return PatrolStatistics {
+                    visit_counts,
+                    turns,
+                    longest_straight_run,
+                    exit: None,
+                    distinct_states: seen_states.len(),
+                };
+            }
+
+            visit_counts[y][x] += 1;
+        }
+
+        match next_obstruction {
+            Some(new_position) => {
+                guard.position = new_position;
+                guard.current_direction = guard.current_direction.turn_right();
+                turns += 1;
+
+                if !seen_states.insert((guard.position, guard.current_direction)) {
+                    return PatrolStatistics {
+                        visit_counts,
+                        turns,
+                        longest_straight_run,
+                        exit: None,
+                        distinct_states: seen_states.len(),
+                    };
+                }
+            }
+            None => {
+                return PatrolStatistics {
+                    visit_counts,
+                    turns,
+                    longest_straight_run,
+                    exit: Some((*walked_path.last().unwrap(), guard.current_direction)),
+                    distinct_states: seen_states.len(),
+                };
+            }
+        }
+    }
+}
@@ -1,4 +1,14 @@
-use crate::sixth::{parse_puzzle_input, part_1, part_2};
+use crate::progress::SilentProgress;
+use crate::sixth::{
+    guard_runs_into_loop,
+    obstacles::{
+        find_escape_obstructions, find_trapping_obstructions, minimum_trapping_obstructions,
+    },
+    parse_puzzle_input, part_1, part_2,
+    serialisation::{parse_map, serialise_map},
+    statistics::patrol_statistics,
+    Direction, MapTile,
+};
 
 const SAMPLE: &str = "
 ....#.....
@@ -12,13 +22,19 @@ const SAMPLE: &str = "
 #.........
 ......#...";
 
+const TRAPPED: &str = "
+.#..
+...#
+#^..
+..##";
+
 #[test]
 fn test_part_2_sample() {
     let input = SAMPLE.trim();
 
     let (map, guard) = parse_puzzle_input(input);
 
-    let result = part_2(map, guard);
+    let result = part_2(map, guard, &SilentProgress);
 
     assert_eq!(result, [(1, 8), (3, 6), (3, 8), (6, 7), (7, 7), (7, 9)]);
 }
@@ -36,7 +52,7 @@ fn test_part_2_subreddit_help() {
     println!("{}", map.text_representation(Some(&guard)));
 
     assert_eq!(part_1(&map, guard.clone()), 3);
-    assert!(part_2(map, guard).is_empty());
+    assert!(part_2(map, guard, &SilentProgress).is_empty());
 }
 
 #[test]
@@ -51,7 +67,10 @@ fn test_part_2_other_example() {
 
     println!("{}", map.text_representation(Some(&guard)));
 
-    assert_eq!(part_2(map, guard), [(0, 1), (1, 1), (3, 1)]);
+    assert_eq!(
+        part_2(map, guard, &SilentProgress),
+        [(0, 1), (1, 1), (3, 1)]
+    );
 }
 
 #[test]
@@ -68,7 +87,7 @@ fn test_part_2_another() {
 
     println!("{}", map.text_representation(Some(&guard)));
 
-    assert_eq!(part_2(map, guard).len(), 1);
+    assert_eq!(part_2(map, guard, &SilentProgress).len(), 1);
 }
 
 #[test]
@@ -91,5 +110,169 @@ fn test_part_2_yet_another_debug_map() {
 
     println!("{}", map.text_representation(Some(&guard)));
 
-    assert_eq!(part_2(map, guard).len(), 19);
+    assert_eq!(part_2(map, guard, &SilentProgress).len(), 19);
+}
+
+#[test]
+fn test_single_trapping_obstructions_match_part_2() {
+    let (map, guard) = parse_puzzle_input(SAMPLE.trim());
+
+    let single_obstructions = find_trapping_obstructions(&map, &guard, 1)
+        .into_iter()
+        .map(|obstructions| obstructions[0])
+        .collect::<Vec<_>>();
+
+    assert_eq!(single_obstructions, part_2(map, guard, &SilentProgress));
+}
+
+#[test]
+fn test_pairs_of_trapping_obstructions_match_brute_force() {
+    let (map, guard) = parse_puzzle_input(SAMPLE.trim());
+
+    let traps_guard = |obstructions: &[(usize, usize)]| {
+        let mut map_with_obstructions = map.clone();
+        for &(x, y) in obstructions {
+            map_with_obstructions.map[y][x] = MapTile::HighlightedObstruction;
+        }
+
+        guard_runs_into_loop(&map_with_obstructions, guard.clone())
+    };
+
+    let free_tiles = (0..map.size.1)
+        .flat_map(|y| (0..map.size.0).map(move |x| (x, y)))
+        .filter(|&(x, y)| map.map[y][x] == MapTile::Free && guard.position != (x, y))
+        .collect::<Vec<_>>();
+
+    let mut expected = Vec::new();
+    for (i, &first) in free_tiles.iter().enumerate() {
+        for &second in &free_tiles[i + 1..] {
+            if traps_guard(&[first, second]) && !traps_guard(&[first]) && !traps_guard(&[second]) {
+                expected.push(vec![first.min(second), first.max(second)]);
+            }
+        }
+    }
+    expected.sort();
+
+    assert!(!expected.is_empty());
+    assert_eq!(find_trapping_obstructions(&map, &guard, 2), expected);
+}
+
+#[test]
+fn test_minimum_trapping_obstructions() {
+    let (map, guard) = parse_puzzle_input(SAMPLE.trim());
+
+    let (count, obstruction_sets) = minimum_trapping_obstructions(&map, &guard, 3).unwrap();
+
+    assert_eq!(count, 1);
+    assert_eq!(obstruction_sets.len(), 6);
+
+    let (map, guard) = parse_puzzle_input(TRAPPED.trim());
+
+    assert_eq!(
+        minimum_trapping_obstructions(&map, &guard, 3),
+        Some((0, vec![vec![]]))
+    );
+}
+
+#[test]
+fn test_escape_obstructions() {
+    let (map, guard) = parse_puzzle_input(TRAPPED.trim());
+
+    assert_eq!(
+        find_escape_obstructions(&map, &guard),
+        [(0, 2), (1, 0), (2, 3), (3, 1)]
+    );
+
+    let (map, guard) = parse_puzzle_input(SAMPLE.trim());
+
+    assert!(find_escape_obstructions(&map, &guard).is_empty());
+}
+
+#[test]
+fn test_patrol_statistics_sample() {
+    let (map, guard) = parse_puzzle_input(SAMPLE.trim());
+
+    let statistics = patrol_statistics(&map, guard.clone());
+
+    println!("{}", map.heatmap_representation(Some(&guard), &statistics));
+
+    assert_eq!(statistics.visited_tiles(), part_1(&map, guard));
+    assert_eq!(statistics.visited_tiles(), 41);
+    assert_eq!(statistics.exit, Some(((7, 9), Direction::Downwards)));
+    assert_eq!(statistics.turns, 10);
+    assert_eq!(statistics.longest_straight_run, 6);
+    assert_eq!(statistics.visit_counts[6][4], 2);
+}
+
+#[test]
+fn test_patrol_statistics_trapped() {
+    let (map, guard) = parse_puzzle_input(TRAPPED.trim());
+
+    let statistics = patrol_statistics(&map, guard.clone());
+
+    assert_eq!(statistics.exit, None);
+    assert_eq!(statistics.visited_tiles(), 4);
+    assert_eq!(statistics.distinct_states, 8);
+    assert_eq!(
+        map.heatmap_representation(None, &statistics),
+        "000 .#..\n001 .11#\n002 #21.\n003 ..##\n"
+    );
+}
+
+#[test]
+fn test_serialisation_round_trip() {
+    let (map, guards) = parse_map(SAMPLE);
+
+    let serialised_map = serialise_map(&map, &guards);
+
+    assert_eq!(serialised_map.trim(), SAMPLE.trim());
+    assert_eq!(parse_map(&serialised_map), (map, guards));
+
+    const MULTIPLE_GUARDS: &str = "
+.>..#
+$...v
+<.#..
+.^...";
+
+    let (map, guards) = parse_map(MULTIPLE_GUARDS);
+
+    assert_eq!(guards.len(), 4);
+    assert_eq!(guards[1].current_direction, Direction::Downwards);
+    assert_eq!(map.map[1][0], MapTile::HighlightedObstruction);
+    assert_eq!(
+        parse_map(serialise_map(&map, &guards)),
+        (map.clone(), guards.clone())
+    );
+
+    // the guards come back in reading order, which is the order `parse_map` returns them in
+    let mut shuffled_guards = guards.clone();
+    shuffled_guards.reverse();
+    shuffled_guards.swap(0, 2);
+
+    assert_ne!(shuffled_guards, guards);
+    assert_eq!(
+        parse_map(serialise_map(&map, &shuffled_guards)),
+        (map, guards)
+    );
+}
+
+#[test]
+fn test_editing_the_sample_map() {
+    let (mut map, mut guard) = parse_puzzle_input(SAMPLE.trim());
+
+    // the first of the obstructions found by part 2
+    assert_eq!(
+        map.set_tile((3, 6), MapTile::HighlightedObstruction),
+        MapTile::Free
+    );
+    assert!(guard_runs_into_loop(&map, guard.clone()));
+
+    map.set_tile((3, 6), MapTile::Free);
+    guard.move_to((0, 0), Direction::Right);
+
+    assert!(!guard_runs_into_loop(&map, guard.clone()));
+    assert_eq!(
+        serialise_map(&map, &[guard]).lines().next(),
+        Some(">...#.....")
+    );
 }
@@ -9,78 +9,145 @@ impl MulInstruction {
     }
 }
 
+/// What the corrupted memory boils down to, in the order it appears.
+#[derive(Debug)]
+pub enum Instruction {
+    Mul(MulInstruction),
+    Do,
+    Dont,
+}
+
 #[allow(unused)]
 pub fn third_december() {
-    let mut instructions_enabled = true;
-    let mut last_instruction_pos = None;
+    let instructions = parse_instructions(include_str!("part3-input.txt"));
 
-    let instructions_keywords = Regex::new(r#"do\(\)|don't\(\)"#).unwrap();
+    let sum_of_all_products = part_1(&instructions);
+    let sum_of_enabled_products = part_2(&instructions);
 
-    let mut picked_up_instructions: Vec<MulInstruction> = Vec::new();
+    dbg!(sum_of_all_products, sum_of_enabled_products);
+}
 
-    let payload = include_str!("part3-input.txt");
-    for keyword_match in instructions_keywords.find_iter(payload) {
-        if instructions_enabled {
-            let current_section = match last_instruction_pos {
-                Some(pos) => &payload[pos..keyword_match.start()],
-                None => &payload[0..keyword_match.start()],
-            };
-            dbg!(&current_section);
-            picked_up_instructions.extend(get_uncorrupted_mul_instructions(current_section));
-        }
+/// Finds the uncorrupted `mul(x,y)`, `do()` and `don't()` instructions, everything else is skipped.
+pub fn parse_instructions(input_string: impl AsRef<str>) -> Vec<Instruction> {
+    let re = Regex::new(r#"mul\((\d+),(\d+)\)|do\(\)|don't\(\)"#).unwrap();
+
+    re.captures_iter(input_string.as_ref())
+        .map(|captures| match &captures[0] {
+            "do()" => Instruction::Do,
+            "don't()" => Instruction::Dont,
+            _ => Instruction::Mul(MulInstruction(
+                captures[1].parse().unwrap(),
+                captures[2].parse().unwrap(),
+            )),
+        })
+        .collect()
+}
 
-        instructions_enabled = keyword_match.as_str() == "do()";
-        last_instruction_pos = Some(keyword_match.end());
-    }
+/// Sum of the products of all `mul` instructions.
+pub fn part_1(instructions: &[Instruction]) -> u64 {
+    instructions
+        .iter()
+        .map(|instruction| match instruction {
+            Instruction::Mul(mul) => mul.calc_product(),
+            Instruction::Do | Instruction::Dont => 0,
+        })
+        .sum()
+}
 
-    // get the very last part if we're still active at the end
-    if instructions_enabled {
-        if let Some(last_instruction_pos) = last_instruction_pos {
-            picked_up_instructions.extend(get_uncorrupted_mul_instructions(
-                &payload[last_instruction_pos..],
-            ));
+/// Sum of the products of the `mul` instructions that aren't disabled by a `don't()`. They are
+/// enabled at the start and again after every `do()`.
+pub fn part_2(instructions: &[Instruction]) -> u64 {
+    let mut instructions_enabled = true;
+    let mut sum_of_products = 0;
+
+    for instruction in instructions {
+        match instruction {
+            Instruction::Mul(mul) if instructions_enabled => {
+                sum_of_products += mul.calc_product();
+            }
+            Instruction::Mul(_) => {}
+            Instruction::Do => instructions_enabled = true,
+            Instruction::Dont => instructions_enabled = false,
         }
     }
 
-    let sum_of_products = picked_up_instructions
-        .iter()
-        .fold(0, |accumulator, current| {
-            current.calc_product() + accumulator
-        });
-
-    dbg!(sum_of_products);
+    sum_of_products
 }
 
-pub fn get_uncorrupted_mul_instructions(input_string: impl AsRef<str>) -> Vec<MulInstruction> {
-    let mut found_instructions = Vec::new();
-    let re = Regex::new(r#"mul\((\d+),(\d+)\)"#).unwrap();
+#[cfg(test)]
+mod tests {
+    use super::{parse_instructions, part_1, part_2};
+
+    #[test]
+    fn given_example() {
+        let payload = "xmul(2,4)%&mul[3,7]!@^do_not_mul(5,5)+mul(32,64]then(mul(11,8)mul(8,5))";
+
+        // only mul(2,4), mul(5,5), mul(11,8) and mul(8,5) are uncorrupted
+        let instructions = parse_instructions(payload);
+        assert_eq!(instructions.len(), 4);
 
-    for (_, [first, second]) in re.captures_iter(input_string.as_ref()).map(|c| c.extract()) {
-        found_instructions.push(MulInstruction(
-            first.parse().unwrap(),
-            second.parse().unwrap(),
-        ));
+        assert_eq!(part_1(&instructions), 161);
     }
 
-    found_instructions
+    #[test]
+    fn given_examples_for_both_parts() {
+        let payload = "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+        let instructions = parse_instructions(payload);
+        assert_eq!(part_1(&instructions), 161);
+        assert_eq!(part_2(&instructions), 48);
+    }
+
+    #[test]
+    fn enabled_without_any_do_or_dont() {
+        let instructions = parse_instructions("mul(3,4)xmul(1,2)");
+
+        assert_eq!(part_2(&instructions), 14);
+    }
+
+    #[test]
+    fn input() {
+        let instructions = parse_instructions(include_str!("part3-input.txt"));
+
+        assert_eq!(part_1(&instructions), 159833790);
+        assert_eq!(part_2(&instructions), 89349241);
+    }
 }
 
+// Timings of both parts on the puzzle input, see
+// `cargo test --release -- --ignored --nocapture third::benches`.
 #[cfg(test)]
-mod tests {
-    use super::get_uncorrupted_mul_instructions;
+mod benches {
+    use std::{
+        hint::black_box,
+        time::{Duration, Instant},
+    };
+
+    use super::{parse_instructions, part_1, part_2, Instruction};
+
+    const ROUNDS: u32 = 1000;
+
+    fn time_part(part: fn(&[Instruction]) -> u64, instructions: &[Instruction]) -> (u64, Duration) {
+        let instant = Instant::now();
+        let mut sum = 0;
+
+        for _ in 0..ROUNDS {
+            sum = part(black_box(instructions));
+        }
+
+        (sum, instant.elapsed() / ROUNDS)
+    }
 
     #[test]
-    fn given_example() {
-        let payload = "xmul(2,4)%&mul[3,7]!@^do_not_mul(5,5)+mul(32,64]then(mul(11,8)mul(8,5))";
+    #[ignore]
+    fn bench_parts_on_input() {
+        let instructions = parse_instructions(include_str!("part3-input.txt"));
 
-        let uncorrupted_instructions = get_uncorrupted_mul_instructions(payload);
+        let (part_1_sum, part_1_elapsed) = time_part(part_1, &instructions);
+        let (part_2_sum, part_2_elapsed) = time_part(part_2, &instructions);
 
-        let sum_of_products = uncorrupted_instructions
-            .iter()
-            .fold(0, |accumulator, current| {
-                current.calc_product() + accumulator
-            });
+        dbg!(instructions.len(), part_1_elapsed, part_2_elapsed);
 
-        assert_eq!(sum_of_products, 161);
+        assert_eq!(part_1_sum, 159833790);
+        assert_eq!(part_2_sum, 89349241);
     }
 }